@@ -0,0 +1,113 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rustflake::{Generator, GeneratorBuilder, Layout, SnowFlaker};
+use std::thread;
+
+const IDS_PER_THREAD: usize = 10000;
+
+fn bench_generator(c: &mut Criterion) {
+    let generator = Generator::new();
+    c.bench_function("generate", |b| b.iter(|| generator.generate()));
+}
+
+fn bench_generate_str(c: &mut Criterion) {
+    let generator = Generator::new();
+    c.bench_function("generate_str", |b| b.iter(|| generator.generate_str()));
+}
+
+fn bench_generate_into(c: &mut Criterion) {
+    let generator = Generator::new();
+    let mut buf = [0; 20];
+    c.bench_function("generate_into", |b| {
+        b.iter(|| {
+            black_box(generator.generate_into(&mut buf).unwrap());
+        })
+    });
+}
+
+fn bench_generator_100000(c: &mut Criterion) {
+    let generator = black_box(Generator::new());
+    c.bench_function("generate 100000", |b| {
+        b.iter(|| {
+            for _x in 0..100000 {
+                generator.generate();
+            }
+        })
+    });
+}
+
+fn bench_generate_batch_100000(c: &mut Criterion) {
+    let generator = black_box(Generator::new());
+    c.bench_function("generate_batch 100000", |b| {
+        b.iter(|| {
+            for _x in 0..100 {
+                generator.reserve(1000).unwrap().for_each(|id| {
+                    black_box(id);
+                });
+            }
+        })
+    });
+}
+
+fn bench_threads(c: &mut Criterion) {
+    let layout = Layout::builder()
+        .timestamp(48)
+        .shard(8)
+        .node(40)
+        .sequence(24)
+        .build()
+        .unwrap();
+    let shared = Generator::new();
+    let mut group = c.benchmark_group("generate_id across threads");
+    for &threads in [1, 2, 4, 8].iter() {
+        let sharded = GeneratorBuilder::new()
+            .layout(layout)
+            .build_sharded(threads)
+            .unwrap();
+        group.bench_with_input(
+            BenchmarkId::new("shared", threads),
+            &threads,
+            |b, &threads| {
+                b.iter(|| {
+                    thread::scope(|scope| {
+                        for _ in 0..threads {
+                            scope.spawn(|| {
+                                for _x in 0..IDS_PER_THREAD {
+                                    black_box(shared.generate_id());
+                                }
+                            });
+                        }
+                    })
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("sharded", threads),
+            &threads,
+            |b, &threads| {
+                b.iter(|| {
+                    thread::scope(|scope| {
+                        for _ in 0..threads {
+                            scope.spawn(|| {
+                                for _x in 0..IDS_PER_THREAD {
+                                    black_box(sharded.generate_id());
+                                }
+                            });
+                        }
+                    })
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_generator,
+    bench_generate_str,
+    bench_generate_into,
+    bench_generator_100000,
+    bench_generate_batch_100000,
+    bench_threads
+);
+criterion_main!(benches);
@@ -0,0 +1,15 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/rustflake.proto");
+        let descriptors =
+            protox::compile(["proto/rustflake.proto"], ["proto"]).expect("valid proto");
+        // The generated `connect` needs the 2021 prelude, so clients build
+        // their own channel instead.
+        tonic_build::configure()
+            .build_transport(false)
+            .compile_fds(descriptors)
+            .expect("generated gRPC code");
+    }
+}
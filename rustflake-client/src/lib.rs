@@ -0,0 +1,117 @@
+//! A client for the gRPC id service that `rustflake` serves with the `grpc`
+//! feature.
+
+// Every call fails with the `tonic::Status` the server sent, like the
+// generated client does.
+#![allow(clippy::result_large_err)]
+
+extern crate rustflake;
+extern crate tokio_stream;
+extern crate tonic;
+
+use std::error;
+use std::fmt;
+use std::time::{Duration, UNIX_EPOCH};
+
+use rustflake::grpc::proto::id_service_client::IdServiceClient;
+use rustflake::grpc::proto::{
+    self, DecodeRequest, NextIdRequest, NextIdsRequest, StreamIdsRequest,
+};
+use rustflake::{Components, FlakeId};
+use tokio_stream::{Stream, StreamExt};
+use tonic::codegen::http::uri::InvalidUri;
+use tonic::transport::{self, Channel};
+use tonic::Status;
+
+#[derive(Debug)]
+pub enum ConnectError {
+    InvalidUri(InvalidUri),
+    Transport(transport::Error),
+}
+
+impl fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConnectError::InvalidUri(ref e) => write!(f, "Invalid server URI: {}", e),
+            ConnectError::Transport(ref e) => write!(f, "Error connecting to server: {}", e),
+        }
+    }
+}
+
+impl error::Error for ConnectError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ConnectError::InvalidUri(ref e) => Some(e),
+            ConnectError::Transport(ref e) => Some(e),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    inner: IdServiceClient<Channel>,
+}
+
+impl Client {
+    /// Connects to a server at a URI such as `http://127.0.0.1:50051`.
+    pub async fn connect(uri: String) -> Result<Client, ConnectError> {
+        let channel = Channel::from_shared(uri)
+            .map_err(ConnectError::InvalidUri)?
+            .connect()
+            .await
+            .map_err(ConnectError::Transport)?;
+        Ok(Client {
+            inner: IdServiceClient::new(channel),
+        })
+    }
+
+    pub async fn next_id(&mut self) -> Result<FlakeId, Status> {
+        let id = self.inner.next_id(NextIdRequest {}).await?.into_inner();
+        flake_id(id)
+    }
+
+    pub async fn next_ids(&mut self, count: u32) -> Result<Vec<FlakeId>, Status> {
+        let ids = self
+            .inner
+            .next_ids(NextIdsRequest { count })
+            .await?
+            .into_inner();
+        ids.ids.into_iter().map(flake_id).collect()
+    }
+
+    pub async fn stream_ids(
+        &mut self,
+        count: u64,
+    ) -> Result<impl Stream<Item = Result<FlakeId, Status>>, Status> {
+        let stream = self
+            .inner
+            .stream_ids(StreamIdsRequest { count })
+            .await?
+            .into_inner();
+        Ok(stream.map(|id| id.and_then(flake_id)))
+    }
+
+    pub async fn decode(&mut self, id: &str) -> Result<Components, Status> {
+        let components = self
+            .inner
+            .decode(DecodeRequest { id: id.to_string() })
+            .await?
+            .into_inner();
+        if components.seed.len() != 6 {
+            return Err(Status::data_loss("seed is not 6 bytes"));
+        }
+        let mut seed = [0; 6];
+        seed.copy_from_slice(&components.seed);
+        Ok(Components {
+            timestamp: UNIX_EPOCH + Duration::from_millis(components.timestamp_ms),
+            seed,
+            sequence: components.sequence,
+            shard: 0,
+            random: 0,
+        })
+    }
+}
+
+fn flake_id(id: proto::Id) -> Result<FlakeId, Status> {
+    FlakeId::from_slice(&id.raw).map_err(|e| Status::data_loss(e.to_string()))
+}
@@ -0,0 +1,54 @@
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+use rustflake::{Generator, SnowFlaker};
+use rustflake_client::Client;
+use tokio::net::TcpListener;
+use tokio_stream::StreamExt;
+use tonic::Code;
+
+async fn client() -> Client {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let generator = Arc::new(Generator::with_seed([1, 2, 3, 4, 5, 6]));
+    tokio::spawn(rustflake::grpc::serve(generator, listener));
+    Client::connect(format!("http://{}", addr)).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_next_id_and_next_ids() {
+    let mut client = client().await;
+    let first = client.next_id().await.unwrap();
+    assert_eq!(first.seed(), [1, 2, 3, 4, 5, 6]);
+    let ids = client.next_ids(100).await.unwrap();
+    assert_eq!(ids.len(), 100);
+    assert!(first < ids[0]);
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    let status = client.next_ids(0).await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+#[tokio::test]
+async fn test_stream_ids() {
+    let mut client = client().await;
+    let stream = client.stream_ids(2_500).await.unwrap();
+    let ids: Vec<_> = stream.collect::<Result<_, _>>().await.unwrap();
+    assert_eq!(ids.len(), 2_500);
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    let status = client.stream_ids(0).await.err().unwrap();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+#[tokio::test]
+async fn test_decode() {
+    let mut client = client().await;
+    let components = client.decode("AWgGtbwAAQIDBAUGAAAH").await.unwrap();
+    assert_eq!(
+        components.timestamp,
+        UNIX_EPOCH + Duration::from_millis(1_546_300_800_000)
+    );
+    assert_eq!(components.seed, [1, 2, 3, 4, 5, 6]);
+    assert_eq!(components.sequence, 7);
+    let status = client.decode("AWgGtbwAAQ*DBAUGAAAH").await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
@@ -0,0 +1,122 @@
+use crate::flake_id::FlakeId;
+use crate::Generator;
+
+/// The ids for a range of sequence numbers claimed by `Generator::reserve`.
+#[derive(Debug)]
+pub struct Batch<'a> {
+    generator: &'a Generator,
+    timestamp: u64,
+    sequence: u64,
+    remaining: u64,
+}
+
+impl<'a> Batch<'a> {
+    pub(crate) fn new(
+        generator: &'a Generator,
+        timestamp: u64,
+        sequence: u64,
+        count: u64,
+    ) -> Batch<'a> {
+        Batch {
+            generator,
+            timestamp,
+            sequence,
+            remaining: count,
+        }
+    }
+}
+
+impl<'a> Iterator for Batch<'a> {
+    type Item = FlakeId;
+
+    fn next(&mut self) -> Option<FlakeId> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let flake_id = self.generator.compose(self.timestamp, self.sequence);
+        self.sequence += 1;
+        self.remaining -= 1;
+        Some(flake_id)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining as usize, Some(self.remaining as usize))
+    }
+}
+
+impl<'a> ExactSizeIterator for Batch<'a> {}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::error::Error;
+    use crate::layout::Field;
+    use crate::sequence::{OnExhausted, SequenceMode};
+    use crate::tests::{advance_later, manual_generator, narrow_generator};
+    use std::time::Duration;
+
+    #[test]
+    fn test_reserve_claims_contiguous_range() {
+        let (generator, _clock) = manual_generator([1; 6]);
+        let first_id = generator.generate_id();
+        let batch: Vec<FlakeId> = generator.reserve(1000).unwrap().collect();
+        let last_id = generator.generate_id();
+        assert_eq!(batch.len(), 1000);
+        assert_eq!(batch[0].sequence(), 1);
+        assert_eq!(batch[999].sequence(), 1000);
+        assert_eq!(last_id.sequence(), 1001);
+        assert!(first_id < batch[0]);
+        assert!(batch.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(batch[999] < last_id);
+    }
+
+    #[test]
+    fn test_generate_batch_encodes_ids() {
+        let (generator, _clock) = manual_generator([1; 6]);
+        let batch = generator.generate_batch(3).unwrap();
+        assert_eq!(batch.size_hint(), (3, Some(3)));
+        for (i, id) in batch.enumerate() {
+            assert_eq!(id.parse::<FlakeId>().unwrap().sequence(), i as u32);
+        }
+        assert_eq!(generator.reserve(0).unwrap().count(), 0);
+        assert_eq!(generator.generate_id().sequence(), 3);
+    }
+
+    #[test]
+    fn test_reserve_across_the_wrap_starts_in_the_next_tick() {
+        let (generator, clock) = narrow_generator();
+        let layout = generator.layout();
+        let issued: Vec<FlakeId> = generator.reserve(4000).unwrap().collect();
+        let handle = advance_later(&clock, Duration::from_millis(1));
+        let batch: Vec<FlakeId> = generator.reserve(200).unwrap().collect();
+        handle.join().unwrap();
+        assert_eq!(layout.value(&batch[0], Field::Sequence), 0);
+        assert!(issued[3999] < batch[0]);
+        assert!(batch.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_reserve_rejects_batches_larger_than_a_tick() {
+        let (generator, _clock) = manual_generator([1; 6]);
+        assert!(matches!(
+            generator.reserve((1 << 24) + 1),
+            Err(Error::BatchTooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn test_reserve_with_reset_per_tick() {
+        let (generator, clock) = manual_generator([1; 6]);
+        let generator = generator.with_sequence_mode(SequenceMode::ResetPerTick(OnExhausted::Fail));
+        assert_eq!(generator.reserve(1 << 24).unwrap().len(), 1 << 24);
+        assert!(matches!(
+            generator.reserve(1),
+            Err(Error::SequenceExhausted)
+        ));
+        clock.advance(Duration::from_millis(1));
+        let batch: Vec<FlakeId> = generator.reserve(2).unwrap().collect();
+        assert_eq!(batch[0].sequence(), 0);
+        assert_eq!(batch[1].sequence(), 1);
+    }
+}
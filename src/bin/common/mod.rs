@@ -0,0 +1,139 @@
+//! Command-line handling shared by the binaries.
+
+// Each binary uses a different part of this module.
+#![allow(dead_code)]
+
+use std::fmt::Display;
+use std::process;
+
+use rustflake::{resolve_seed, Encoding, Generator, SnowFlaker};
+
+/// Parses the arguments with `parse`, or exits with 2 after printing the
+/// problem and `usage`.
+pub fn parse_or_exit<C, F>(name: &str, usage: &str, parse: F) -> C
+where
+    F: FnOnce(Vec<String>) -> Result<C, String>,
+{
+    match parse(std::env::args().skip(1).collect()) {
+        Ok(config) => config,
+        Err(message) => {
+            eprintln!("{}: {}\n\n{}", name, message, usage);
+            process::exit(2);
+        }
+    }
+}
+
+/// Exits with 1 after printing the problem if serving failed.
+pub fn exit_on_error(name: &str, result: Result<(), String>) {
+    if let Err(message) = result {
+        eprintln!("{}: {}", name, message);
+        process::exit(1);
+    }
+}
+
+/// Calls `option` with each argument and a closure taking its value, so
+/// every option takes exactly one value.
+pub fn parse_options<F>(args: Vec<String>, mut option: F) -> Result<(), String>
+where
+    F: FnMut(&str, &mut dyn FnMut() -> Result<String, String>) -> Result<(), String>,
+{
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        option(&arg, &mut value)?;
+    }
+    Ok(())
+}
+
+pub fn unknown(arg: &str) -> String {
+    format!("unknown argument {:?}", arg)
+}
+
+// Tests bind port 0 and read the chosen address from here.
+pub fn listening<A: Display>(addr: A) {
+    println!("listening on {}", addr);
+}
+
+pub fn encoding(name: &str) -> Result<Encoding, String> {
+    Encoding::from_name(name).ok_or_else(|| format!("unknown encoding {:?}", name))
+}
+
+/// The options for building a generator: `-s SEED` and `-e ENCODING`.
+pub struct GeneratorOptions {
+    seed: String,
+    encoding: Encoding,
+}
+
+impl GeneratorOptions {
+    pub fn new() -> GeneratorOptions {
+        GeneratorOptions {
+            seed: "mac".to_string(),
+            encoding: Encoding::default(),
+        }
+    }
+
+    /// Takes `arg` if it is a generator option, returning false otherwise.
+    pub fn option(
+        &mut self,
+        arg: &str,
+        value: &mut dyn FnMut() -> Result<String, String>,
+    ) -> Result<bool, String> {
+        match arg {
+            "-s" | "--seed" => self.seed = value()?,
+            "-e" | "--encoding" => self.encoding = encoding(&value()?)?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    pub fn generator(&self) -> Result<Generator, String> {
+        let seed = resolve_seed(&self.seed).map_err(|e| e.to_string())?;
+        Ok(Generator::with_seed(seed).with_encoding(self.encoding))
+    }
+}
+
+/// The options every id server takes: `-b ADDRESS` and the generator
+/// options.
+pub struct ServerConfig {
+    pub bind: String,
+    generator: GeneratorOptions,
+}
+
+impl ServerConfig {
+    pub fn new(bind: &str) -> ServerConfig {
+        ServerConfig {
+            bind: bind.to_string(),
+            generator: GeneratorOptions::new(),
+        }
+    }
+
+    /// Parses arguments that are all server options.
+    pub fn parse(args: Vec<String>, bind: &str) -> Result<ServerConfig, String> {
+        let mut config = ServerConfig::new(bind);
+        parse_options(args, |arg, value| {
+            if config.option(arg, value)? {
+                Ok(())
+            } else {
+                Err(unknown(arg))
+            }
+        })?;
+        Ok(config)
+    }
+
+    /// Takes `arg` if it is a server option, returning false otherwise.
+    pub fn option(
+        &mut self,
+        arg: &str,
+        value: &mut dyn FnMut() -> Result<String, String>,
+    ) -> Result<bool, String> {
+        match arg {
+            "-b" | "--bind" => self.bind = value()?,
+            _ => return self.generator.option(arg, value),
+        }
+        Ok(true)
+    }
+
+    pub fn generator(&self) -> Result<Generator, String> {
+        self.generator.generator()
+    }
+}
@@ -0,0 +1,38 @@
+extern crate rustflake;
+extern crate tokio;
+
+mod common;
+
+use std::sync::Arc;
+
+use common::ServerConfig;
+use tokio::net::TcpListener;
+
+const NAME: &str = "rustflake-grpc";
+
+const USAGE: &str = "\
+usage: rustflake-grpc [-b ADDRESS] [-s SEED] [-e ENCODING]
+
+Serves the IdService in proto/rustflake.proto on ADDRESS
+(default 127.0.0.1:50051).
+
+SEED and ENCODING are as for the rustflake command.";
+
+#[tokio::main]
+async fn main() {
+    let config = common::parse_or_exit(NAME, USAGE, |args| {
+        ServerConfig::parse(args, "127.0.0.1:50051")
+    });
+    common::exit_on_error(NAME, serve(config).await);
+}
+
+async fn serve(config: ServerConfig) -> Result<(), String> {
+    let generator = Arc::new(config.generator()?);
+    let listener = TcpListener::bind(&config.bind)
+        .await
+        .map_err(|e| e.to_string())?;
+    common::listening(listener.local_addr().map_err(|e| e.to_string())?);
+    rustflake::grpc::serve(generator, listener)
+        .await
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,239 @@
+extern crate rustflake;
+extern crate tiny_http;
+
+mod common;
+
+use std::io;
+use std::sync::Arc;
+use std::thread;
+
+use common::ServerConfig;
+use rustflake::{Generator, MAX_REQUEST_IDS};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+const NAME: &str = "rustflake-http";
+
+const USAGE: &str = "\
+usage: rustflake-http [-b ADDRESS] [-s SEED] [-e ENCODING] [-t THREADS]
+
+Serves GET /id, /ids?count=N, /decode/ID and /health on ADDRESS
+(default 127.0.0.1:8080). Responses are plain text unless the request
+accepts application/json or asks for ?format=json.
+
+SEED and ENCODING are as for the rustflake command.";
+
+struct Config {
+    server: ServerConfig,
+    threads: usize,
+}
+
+fn main() {
+    let config = common::parse_or_exit(NAME, USAGE, parse_args);
+    common::exit_on_error(NAME, serve(config));
+}
+
+fn parse_args(args: Vec<String>) -> Result<Config, String> {
+    let mut server = ServerConfig::new("127.0.0.1:8080");
+    let mut threads = 4;
+    common::parse_options(args, |arg, value| {
+        if server.option(arg, value)? {
+            return Ok(());
+        }
+        match arg {
+            "-t" | "--threads" => {
+                let value = value()?;
+                threads = value
+                    .parse()
+                    .ok()
+                    .filter(|&threads| threads > 0)
+                    .ok_or_else(|| format!("invalid thread count {:?}", value))?;
+            }
+            _ => return Err(common::unknown(arg)),
+        }
+        Ok(())
+    })?;
+    Ok(Config { server, threads })
+}
+
+fn serve(config: Config) -> Result<(), String> {
+    let generator = Arc::new(config.server.generator()?);
+    let server = Arc::new(Server::http(&config.server.bind).map_err(|e| e.to_string())?);
+    common::listening(server.server_addr());
+    let workers: Vec<_> = (0..config.threads)
+        .map(|_| {
+            let server = Arc::clone(&server);
+            let generator = Arc::clone(&generator);
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    let (status, reply) = respond(&generator, &request);
+                    let _ = request.respond(reply.into_response(status));
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        let _ = worker.join();
+    }
+    Ok(())
+}
+
+/// A response body, rendered as JSON or plain text.
+enum Body {
+    Id(String),
+    Ids(Vec<String>),
+    Components {
+        id: String,
+        timestamp_ms: u64,
+        seed: String,
+        sequence: u32,
+    },
+    Health,
+    Error(String),
+}
+
+struct Reply {
+    body: Body,
+    json: bool,
+}
+
+impl Reply {
+    fn into_response(self, status: u16) -> Response<io::Cursor<Vec<u8>>> {
+        let (content_type, text) = if self.json {
+            ("application/json", self.body.json())
+        } else {
+            ("text/plain; charset=utf-8", self.body.text())
+        };
+        let header = Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes())
+            .expect("static header is valid");
+        Response::from_string(text)
+            .with_status_code(status)
+            .with_header(header)
+    }
+}
+
+impl Body {
+    fn json(&self) -> String {
+        match *self {
+            Body::Id(ref id) => format!("{{\"id\":{}}}\n", json_string(id)),
+            Body::Ids(ref ids) => {
+                let ids: Vec<String> = ids.iter().map(|id| json_string(id)).collect();
+                format!("{{\"ids\":[{}]}}\n", ids.join(","))
+            }
+            Body::Components {
+                ref id,
+                timestamp_ms,
+                ref seed,
+                sequence,
+            } => format!(
+                "{{\"id\":{},\"timestamp_ms\":{},\"seed\":{},\"sequence\":{}}}\n",
+                json_string(id),
+                timestamp_ms,
+                json_string(seed),
+                sequence
+            ),
+            Body::Health => "{\"status\":\"ok\"}\n".to_string(),
+            Body::Error(ref message) => format!("{{\"error\":{}}}\n", json_string(message)),
+        }
+    }
+
+    fn text(&self) -> String {
+        match *self {
+            Body::Id(ref id) => format!("{}\n", id),
+            Body::Ids(ref ids) => ids.iter().map(|id| format!("{}\n", id)).collect(),
+            Body::Components {
+                ref id,
+                timestamp_ms,
+                ref seed,
+                sequence,
+            } => format!(
+                "id: {}\ntimestamp_ms: {}\nseed: {}\nsequence: {}\n",
+                id, timestamp_ms, seed, sequence
+            ),
+            Body::Health => "ok\n".to_string(),
+            Body::Error(ref message) => format!("{}\n", message),
+        }
+    }
+}
+
+fn respond(generator: &Generator, request: &Request) -> (u16, Reply) {
+    let (path, query) = match request.url().find('?') {
+        Some(i) => (&request.url()[..i], &request.url()[i + 1..]),
+        None => (request.url(), ""),
+    };
+    let json = param(query, "format") == Some("json")
+        || request.headers().iter().any(|header| {
+            header.field.equiv("Accept") && header.value.as_str().contains("application/json")
+        });
+    let (status, body) = if *request.method() != Method::Get {
+        (405, Body::Error("method not allowed".to_string()))
+    } else {
+        route(generator, path, query)
+    };
+    (status, Reply { body, json })
+}
+
+fn route(generator: &Generator, path: &str, query: &str) -> (u16, Body) {
+    match path {
+        "/id" => match generator.try_generate() {
+            Ok(id) => (200, Body::Id(id)),
+            Err(e) => (503, Body::Error(e.to_string())),
+        },
+        "/ids" => {
+            let count = match param(query, "count").map(str::parse::<usize>) {
+                None => 1,
+                Some(Ok(count)) if count > 0 && count <= MAX_REQUEST_IDS => count,
+                Some(_) => {
+                    let message = format!("count must be between 1 and {}", MAX_REQUEST_IDS);
+                    return (400, Body::Error(message));
+                }
+            };
+            match generator.generate_batch(count) {
+                Ok(ids) => (200, Body::Ids(ids.collect())),
+                Err(e) => (503, Body::Error(e.to_string())),
+            }
+        }
+        "/health" => (200, Body::Health),
+        _ if path.starts_with("/decode/") => {
+            let id = &path["/decode/".len()..];
+            match generator.decoder().decode(id) {
+                Ok(components) => (
+                    200,
+                    Body::Components {
+                        id: id.to_string(),
+                        timestamp_ms: components.timestamp_ms(),
+                        seed: components.seed_hex(),
+                        sequence: components.sequence,
+                    },
+                ),
+                Err(e) => (400, Body::Error(e.to_string())),
+            }
+        }
+        _ => (404, Body::Error("not found".to_string())),
+    }
+}
+
+fn param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| {
+            let mut parts = pair.splitn(2, '=');
+            Some((parts.next()?, parts.next().unwrap_or("")))
+        })
+        .find(|&(key, _)| key == name)
+        .map(|(_, value)| value)
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
@@ -0,0 +1,32 @@
+extern crate rustflake;
+
+mod common;
+
+use std::net::TcpListener;
+use std::sync::Arc;
+
+use common::ServerConfig;
+
+const NAME: &str = "rustflake-resp";
+
+const USAGE: &str = "\
+usage: rustflake-resp [-b ADDRESS] [-s SEED] [-e ENCODING]
+
+Answers PING, FLAKE.NEXT, FLAKE.BATCH count and FLAKE.DECODE id over the
+Redis protocol on ADDRESS (default 127.0.0.1:6380).
+
+SEED and ENCODING are as for the rustflake command.";
+
+fn main() {
+    let config = common::parse_or_exit(NAME, USAGE, |args| {
+        ServerConfig::parse(args, "127.0.0.1:6380")
+    });
+    common::exit_on_error(NAME, serve(config));
+}
+
+fn serve(config: ServerConfig) -> Result<(), String> {
+    let generator = Arc::new(config.generator()?);
+    let listener = TcpListener::bind(&config.bind).map_err(|e| e.to_string())?;
+    common::listening(listener.local_addr().map_err(|e| e.to_string())?);
+    rustflake::resp::serve(generator, listener)
+}
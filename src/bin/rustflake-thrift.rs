@@ -0,0 +1,72 @@
+extern crate rustflake;
+
+mod common;
+
+use std::net::TcpListener;
+use std::sync::Arc;
+
+use rustflake::thrift::snowflake_worker_id;
+use rustflake::{CompactGenerator, CompactLayout};
+
+const NAME: &str = "rustflake-thrift";
+
+const USAGE: &str = "\
+usage: rustflake-thrift -d DATACENTER_ID -w WORKER_ID [-b ADDRESS]
+
+Serves Twitter snowflake's Thrift interface (get_id, get_worker_id,
+get_timestamp and get_datacenter_id) over framed binary transport on
+ADDRESS (default 127.0.0.1:7609). DATACENTER_ID and WORKER_ID are each
+between 0 and 31, as for snowflake.";
+
+struct Config {
+    bind: String,
+    datacenter_id: u64,
+    worker_id: u64,
+}
+
+fn main() {
+    let config = common::parse_or_exit(NAME, USAGE, parse_args);
+    common::exit_on_error(NAME, serve(config));
+}
+
+fn parse_args(args: Vec<String>) -> Result<Config, String> {
+    let mut bind = "127.0.0.1:7609".to_string();
+    let mut datacenter_id = None;
+    let mut worker_id = None;
+    common::parse_options(args, |arg, value| {
+        match arg {
+            "-b" | "--bind" => bind = value()?,
+            "-d" | "--datacenter-id" => {
+                let id = value()?;
+                datacenter_id = Some(
+                    id.parse()
+                        .map_err(|_e| format!("invalid datacenter id {:?}", id))?,
+                );
+            }
+            "-w" | "--worker-id" => {
+                let id = value()?;
+                worker_id = Some(
+                    id.parse()
+                        .map_err(|_e| format!("invalid worker id {:?}", id))?,
+                );
+            }
+            _ => return Err(common::unknown(arg)),
+        }
+        Ok(())
+    })?;
+    Ok(Config {
+        bind,
+        datacenter_id: datacenter_id.ok_or("missing --datacenter-id")?,
+        worker_id: worker_id.ok_or("missing --worker-id")?,
+    })
+}
+
+fn serve(config: Config) -> Result<(), String> {
+    let worker_id =
+        snowflake_worker_id(config.datacenter_id, config.worker_id).map_err(|e| e.to_string())?;
+    let generator = CompactGenerator::with_worker_id(worker_id, CompactLayout::default())
+        .map_err(|e| e.to_string())?;
+    let listener = TcpListener::bind(&config.bind).map_err(|e| e.to_string())?;
+    common::listening(listener.local_addr().map_err(|e| e.to_string())?);
+    Err(rustflake::thrift::serve(Arc::new(generator), listener).to_string())
+}
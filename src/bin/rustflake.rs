@@ -0,0 +1,258 @@
+extern crate rustflake;
+
+mod common;
+
+use std::env;
+use std::io::{self, BufWriter, Write};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rustflake::{Decoder, Encoding, FlakeId, TimeUnit, FLAKE_ID_LEN};
+
+use common::GeneratorOptions;
+
+const USAGE: &str = "\
+usage: rustflake gen [-n COUNT] [-e ENCODING] [-s SEED]
+       rustflake decode [-e ENCODING] ID...
+       rustflake convert [-f FORMAT] -t FORMAT ID...
+       rustflake validate [-e ENCODING] ID...
+
+ENCODING is base64url (default), base64sortable, base32 or hex.
+FORMAT is an ENCODING or u128.
+SEED is mac (default), hostname, random, env:NAME, file:PATH or a MAC
+address such as 01:23:45:67:89:ab.";
+
+// A usage error exits with 2, anything else with 1.
+enum Failure {
+    Usage(String),
+    Other(String),
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("gen") => gen(&args[1..]),
+        Some("decode") => decode(&args[1..]),
+        Some("convert") => convert(&args[1..]),
+        Some("validate") => validate(&args[1..]),
+        Some("-h") | Some("--help") | Some("help") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Some(command) => Err(Failure::Usage(format!("unknown command {:?}", command))),
+        None => Err(Failure::Usage("missing command".to_string())),
+    };
+    match result {
+        Ok(()) => {}
+        Err(Failure::Usage(message)) => {
+            eprintln!("rustflake: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+        Err(Failure::Other(message)) => {
+            eprintln!("rustflake: {}", message);
+            process::exit(1);
+        }
+    }
+}
+
+// Anything not starting with `-` is an id; so is `-` alone.
+fn is_id(arg: &str) -> bool {
+    !arg.starts_with('-') || arg == "-"
+}
+
+fn ids(ids: Vec<String>) -> Result<Vec<String>, Failure> {
+    if ids.is_empty() {
+        return Err(Failure::Usage("missing ID".to_string()));
+    }
+    Ok(ids)
+}
+
+// Parses `-e ENCODING` and the ids of `decode` and `validate`.
+fn encoding_and_ids(args: &[String]) -> Result<(Encoding, Vec<String>), Failure> {
+    let mut encoding = Encoding::default();
+    let mut ids = Vec::new();
+    common::parse_options(args.to_vec(), |arg, value| {
+        match arg {
+            "-e" | "--encoding" => encoding = common::encoding(&value()?)?,
+            _ if is_id(arg) => ids.push(arg.to_string()),
+            _ => return Err(common::unknown(arg)),
+        }
+        Ok(())
+    })
+    .map_err(Failure::Usage)?;
+    Ok((encoding, self::ids(ids)?))
+}
+
+fn gen(args: &[String]) -> Result<(), Failure> {
+    let mut options = GeneratorOptions::new();
+    let mut count = 1;
+    common::parse_options(args.to_vec(), |arg, value| {
+        if options.option(arg, value)? {
+            return Ok(());
+        }
+        match arg {
+            "-n" | "--count" => {
+                let value = value()?;
+                count = value
+                    .parse::<u64>()
+                    .map_err(|_e| format!("invalid count {:?}", value))?;
+            }
+            _ => return Err(common::unknown(arg)),
+        }
+        Ok(())
+    })
+    .map_err(Failure::Usage)?;
+    let generator = options.generator().map_err(Failure::Other)?;
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    for _ in 0..count {
+        let id = generator
+            .try_generate_str()
+            .map_err(|e| Failure::Other(e.to_string()))?;
+        writeln!(out, "{}", id).map_err(io_failure)?;
+    }
+    out.flush().map_err(io_failure)
+}
+
+fn decode(args: &[String]) -> Result<(), Failure> {
+    let (encoding, ids) = encoding_and_ids(args)?;
+    let decoder = decoder(encoding);
+    let mut out = String::new();
+    for id in &ids {
+        let components = decoder
+            .decode(id)
+            .map_err(|e| Failure::Other(format!("{}: {}", id, e)))?;
+        out.push_str(&format!(
+            "id: {}\ntimestamp: {}\nseed: {}\nsequence: {}\n",
+            id,
+            format_time(components.timestamp),
+            components.seed_hex(),
+            components.sequence
+        ));
+    }
+    print!("{}", out);
+    Ok(())
+}
+
+fn convert(args: &[String]) -> Result<(), Failure> {
+    let mut from = Format::Encoding(Encoding::default());
+    let mut to = None;
+    let mut ids = Vec::new();
+    common::parse_options(args.to_vec(), |arg, value| {
+        match arg {
+            "-f" | "--from" => from = format(value()?)?,
+            "-t" | "--to" => to = Some(format(value()?)?),
+            _ if is_id(arg) => ids.push(arg.to_string()),
+            _ => return Err(common::unknown(arg)),
+        }
+        Ok(())
+    })
+    .map_err(Failure::Usage)?;
+    let to = to.ok_or_else(|| Failure::Usage("missing --to".to_string()))?;
+    let mut out = String::new();
+    for id in &self::ids(ids)? {
+        let flake_id = parse_id(id, from).map_err(|e| Failure::Other(format!("{}: {}", id, e)))?;
+        match to {
+            Format::Encoding(encoding) => out.push_str(&flake_id.encode_str(encoding)),
+            // Only these read back from u128 as the same id.
+            Format::U128 if flake_id.byte_len() == FLAKE_ID_LEN => {
+                out.push_str(&flake_id.to_u128().to_string())
+            }
+            Format::U128 => {
+                return Err(Failure::Other(format!(
+                    "{}: only {}-byte ids convert to u128",
+                    id, FLAKE_ID_LEN
+                )))
+            }
+        }
+        out.push('\n');
+    }
+    print!("{}", out);
+    Ok(())
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Encoding(Encoding),
+    U128,
+}
+
+fn format(name: String) -> Result<Format, String> {
+    match name.as_str() {
+        "u128" => Ok(Format::U128),
+        name => common::encoding(name).map(Format::Encoding),
+    }
+}
+
+fn parse_id(id: &str, format: Format) -> Result<FlakeId, String> {
+    match format {
+        Format::Encoding(encoding) => FlakeId::decode(id, encoding).map_err(|e| e.to_string()),
+        Format::U128 => id
+            .parse::<u128>()
+            .map_err(|e| e.to_string())
+            .and_then(|value| FlakeId::from_u128(value).map_err(|e| e.to_string())),
+    }
+}
+
+fn validate(args: &[String]) -> Result<(), Failure> {
+    let (encoding, ids) = encoding_and_ids(args)?;
+    let decoder = decoder(encoding);
+    let mut invalid = 0;
+    for id in &ids {
+        match decoder.decode(id) {
+            Ok(_) => println!("{}: valid", id),
+            Err(e) => {
+                println!("{}: invalid, {}", id, e);
+                invalid += 1;
+            }
+        }
+    }
+    if invalid > 0 {
+        return Err(Failure::Other(format!("{} invalid id(s)", invalid)));
+    }
+    Ok(())
+}
+
+// Ids as `gen` issues them: the default layout with millisecond timestamps
+// since the Unix epoch.
+fn decoder(encoding: Encoding) -> Decoder {
+    Decoder::new(UNIX_EPOCH, TimeUnit::Millis, encoding)
+}
+
+fn io_failure(e: io::Error) -> Failure {
+    Failure::Other(e.to_string())
+}
+
+// Formats a time as RFC 3339 in UTC with millisecond precision.
+fn format_time(time: SystemTime) -> String {
+    let millis = match time.duration_since(UNIX_EPOCH) {
+        Ok(elapsed) => elapsed.as_millis() as u64,
+        Err(_e) => return "before 1970-01-01T00:00:00.000Z".to_string(),
+    };
+    let (days, millis_of_day) = (millis / 86_400_000, millis % 86_400_000);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        millis_of_day / 3_600_000,
+        millis_of_day / 60_000 % 60,
+        millis_of_day / 1_000 % 60,
+        millis_of_day % 1_000
+    )
+}
+
+// The proleptic Gregorian date `days` after 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
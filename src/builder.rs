@@ -0,0 +1,425 @@
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::checkpoint::{Checkpoint, Checkpointer};
+use crate::clock::{Clock, ClockRollback, SystemClock, TimeUnit};
+use crate::encoding::Encoding;
+use crate::error::Error;
+use crate::layout::{Field, Layout};
+use crate::seed::{MacAddressSeed, SeedProvider};
+use crate::sequence::SequenceMode;
+use crate::sharded::ShardedGenerator;
+use crate::state::State;
+use crate::Generator;
+
+/// Configures a `Generator`. Anything left unset takes the same default as
+/// `SnowFlaker::new`: the MAC address seed, the Unix epoch, milliseconds and
+/// the default layout.
+pub struct GeneratorBuilder {
+    seed_provider: Box<dyn SeedProvider>,
+    epoch: SystemTime,
+    time_unit: TimeUnit,
+    encoding: Encoding,
+    clock: Arc<dyn Clock>,
+    rollback: ClockRollback,
+    sequence_mode: SequenceMode,
+    layout: Layout,
+    shard: u64,
+    checkpoint: Option<(Box<dyn Checkpoint>, Duration)>,
+}
+
+impl GeneratorBuilder {
+    pub fn new() -> GeneratorBuilder {
+        GeneratorBuilder {
+            seed_provider: Box::new(MacAddressSeed),
+            epoch: UNIX_EPOCH,
+            time_unit: TimeUnit::default(),
+            encoding: Encoding::default(),
+            clock: Arc::new(SystemClock),
+            rollback: ClockRollback::default(),
+            sequence_mode: SequenceMode::default(),
+            layout: Layout::default(),
+            shard: 0,
+            checkpoint: None,
+        }
+    }
+
+    pub fn seed(self, seed: [u8; 6]) -> GeneratorBuilder {
+        self.seed_provider(seed)
+    }
+
+    pub fn seed_provider<P: SeedProvider + 'static>(mut self, provider: P) -> GeneratorBuilder {
+        self.seed_provider = Box::new(provider);
+        self
+    }
+
+    pub fn epoch(mut self, epoch: SystemTime) -> GeneratorBuilder {
+        self.epoch = epoch;
+        self
+    }
+
+    pub fn time_unit(mut self, time_unit: TimeUnit) -> GeneratorBuilder {
+        self.time_unit = time_unit;
+        self
+    }
+
+    pub fn encoding(mut self, encoding: Encoding) -> GeneratorBuilder {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> GeneratorBuilder {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    pub fn clock_rollback(mut self, rollback: ClockRollback) -> GeneratorBuilder {
+        self.rollback = rollback;
+        self
+    }
+
+    pub fn sequence_mode(mut self, sequence_mode: SequenceMode) -> GeneratorBuilder {
+        self.sequence_mode = sequence_mode;
+        self
+    }
+
+    pub fn layout(mut self, layout: Layout) -> GeneratorBuilder {
+        self.layout = layout;
+        self
+    }
+
+    /// The value of the layout's shard field in every id.
+    pub fn shard(mut self, shard: u64) -> GeneratorBuilder {
+        self.shard = shard;
+        self
+    }
+
+    /// Persists a high-water mark `lease` ahead of the latest timestamp and
+    /// never issues ids below the stored one, so ids issued before a restart
+    /// are never issued again.
+    pub fn checkpoint<C: Checkpoint + 'static>(
+        mut self,
+        checkpoint: C,
+        lease: Duration,
+    ) -> GeneratorBuilder {
+        self.checkpoint = Some((Box::new(checkpoint), lease));
+        self
+    }
+
+    /// Resolves the seed and checks the current time and the shard can be
+    /// represented with the configured layout, epoch and time unit.
+    pub fn build(mut self) -> Result<Generator, Error> {
+        self.validate(self.shard)?;
+        let seed = self.seed_provider.seed()?;
+        let checkpoint = self.checkpointer();
+        let floor = self.floor(&checkpoint)?;
+        Ok(self.generator(seed, self.shard, floor, &checkpoint))
+    }
+
+    /// Builds `shards` generators sharing this configuration, numbered
+    /// upwards from the configured shard. All of them must fit the layout's
+    /// shard field.
+    pub fn build_sharded(mut self, shards: usize) -> Result<ShardedGenerator, Error> {
+        if shards == 0 {
+            return Err(Error::ShardOutOfRange(0));
+        }
+        self.validate(self.shard + shards as u64 - 1)?;
+        let seed = self.seed_provider.seed()?;
+        let checkpoint = self.checkpointer();
+        let floor = self.floor(&checkpoint)?;
+        let generators = (0..shards as u64)
+            .map(|i| self.generator(seed, self.shard + i, floor, &checkpoint))
+            .collect();
+        Ok(ShardedGenerator::new(generators))
+    }
+
+    fn validate(&self, last_shard: u64) -> Result<(), Error> {
+        let now = self.clock.now();
+        if self.epoch > now {
+            return Err(Error::InvalidEpoch);
+        }
+        if self.time_unit.ticks_since(self.epoch, now) > self.layout.max_value(Field::Timestamp) {
+            return Err(Error::TimestampOverflow);
+        }
+        if last_shard > self.layout.max_value(Field::Shard) {
+            return Err(Error::ShardOutOfRange(last_shard));
+        }
+        Ok(())
+    }
+
+    fn checkpointer(&mut self) -> Option<Arc<Checkpointer>> {
+        let (epoch, time_unit) = (self.epoch, self.time_unit);
+        self.checkpoint.take().map(|(checkpoint, lease)| {
+            Arc::new(Checkpointer::new(checkpoint, lease, epoch, time_unit))
+        })
+    }
+
+    // The stored high-water mark becomes the floor for new timestamps. It is
+    // kept apart from the latest timestamp, so a clock that is only behind
+    // the mark after a restart is not mistaken for a rollback.
+    fn floor(&self, checkpoint: &Option<Arc<Checkpointer>>) -> Result<u64, Error> {
+        let floor = match *checkpoint {
+            Some(ref checkpoint) => checkpoint.load()?,
+            None => 0,
+        };
+        if floor > self.layout.max_value(Field::Timestamp) {
+            return Err(Error::TimestampOverflow);
+        }
+        Ok(floor)
+    }
+
+    fn generator(
+        &self,
+        seed: [u8; 6],
+        shard: u64,
+        floor: u64,
+        checkpoint: &Option<Arc<Checkpointer>>,
+    ) -> Generator {
+        Generator {
+            seed,
+            state: State::new(0, 0),
+            floor,
+            epoch: self.epoch,
+            time_unit: self.time_unit,
+            encoding: self.encoding,
+            clock: Arc::clone(&self.clock),
+            rollback: self.rollback.clone(),
+            clock_skew: AtomicU64::new(0),
+            sequence_mode: self.sequence_mode,
+            layout: self.layout,
+            shard,
+            checkpoint: checkpoint.clone(),
+        }
+    }
+}
+
+impl Default for GeneratorBuilder {
+    fn default() -> GeneratorBuilder {
+        GeneratorBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::checkpoint::FileCheckpoint;
+    use crate::clock::ManualClock;
+    use crate::SnowFlaker;
+    use std::time::Duration;
+
+    #[test]
+    fn test_defaults_match_with_seed() {
+        let generator = GeneratorBuilder::new().seed([1; 6]).build().unwrap();
+        assert_eq!(generator, Generator::with_seed([1; 6]));
+    }
+
+    #[test]
+    fn test_rejects_epoch_in_the_future() {
+        let result = GeneratorBuilder::new()
+            .seed([1; 6])
+            .epoch(SystemTime::now() + Duration::from_secs(60))
+            .build();
+        assert!(matches!(result, Err(Error::InvalidEpoch)));
+    }
+
+    #[test]
+    fn test_rejects_micros_since_unix_epoch() {
+        let result = GeneratorBuilder::new()
+            .seed([1; 6])
+            .time_unit(TimeUnit::Micros)
+            .build();
+        assert!(matches!(result, Err(Error::TimestampOverflow)));
+    }
+
+    #[test]
+    fn test_custom_epoch_and_time_unit() {
+        let epoch = UNIX_EPOCH + Duration::from_secs(1_546_300_800);
+        let clock = ManualClock::new(epoch + Duration::from_micros(1_234_567));
+        let generator = GeneratorBuilder::new()
+            .seed([1; 6])
+            .epoch(epoch)
+            .time_unit(TimeUnit::Micros)
+            .clock(clock.clone())
+            .build()
+            .unwrap();
+        let flake_id = generator.generate_id();
+        assert_eq!(crate::get_uint(flake_id.as_bytes(), 0, 6), 1_234_567);
+        assert_eq!(
+            generator.decoder().components(&flake_id).timestamp,
+            clock.now()
+        );
+    }
+
+    #[test]
+    fn test_ten_millisecond_ticks() {
+        let epoch = UNIX_EPOCH + Duration::from_secs(1_546_300_800);
+        let clock = ManualClock::new(epoch + Duration::from_millis(1_005));
+        let generator = GeneratorBuilder::new()
+            .seed([1; 6])
+            .epoch(epoch)
+            .time_unit(TimeUnit::TenMillis)
+            .clock(clock.clone())
+            .build()
+            .unwrap();
+        let first_id = generator.generate_id();
+        clock.advance(Duration::from_millis(4));
+        let second_id = generator.generate_id();
+        assert_eq!(crate::get_uint(first_id.as_bytes(), 0, 6), 100);
+        assert_eq!(crate::get_uint(second_id.as_bytes(), 0, 6), 100);
+        assert_eq!(
+            generator.decoder().components(&second_id).timestamp,
+            epoch + Duration::from_millis(1_000)
+        );
+    }
+
+    #[test]
+    fn test_custom_layout_with_shard() {
+        let layout = Layout::builder()
+            .reserved(1)
+            .timestamp(41)
+            .shard(5)
+            .node(5)
+            .sequence(12)
+            .build()
+            .unwrap();
+        let epoch = UNIX_EPOCH + Duration::from_secs(1_546_300_800);
+        let clock = ManualClock::new(epoch + Duration::from_millis(7));
+        let generator = GeneratorBuilder::new()
+            .seed([0, 0, 0, 0, 0, 3])
+            .epoch(epoch)
+            .clock(clock)
+            .layout(layout)
+            .shard(17)
+            .build()
+            .unwrap();
+        let flake_id = generator.generate_id();
+        assert_eq!(flake_id.byte_len(), 8);
+        let components = generator.decoder().decode(&generator.generate()).unwrap();
+        assert_eq!(components.timestamp, epoch + Duration::from_millis(7));
+        assert_eq!(components.shard, 17);
+        assert_eq!(components.seed, [0, 0, 0, 0, 0, 3]);
+        assert_eq!(components.sequence, 1);
+        let result = GeneratorBuilder::new()
+            .seed([1; 6])
+            .layout(layout)
+            .shard(32)
+            .build();
+        assert!(matches!(result, Err(Error::ShardOutOfRange(32))));
+    }
+
+    #[test]
+    fn test_random_field_varies() {
+        let layout = Layout::builder()
+            .timestamp(48)
+            .sequence(16)
+            .random(64)
+            .build()
+            .unwrap();
+        let generator = GeneratorBuilder::new()
+            .seed([1; 6])
+            .layout(layout)
+            .build()
+            .unwrap();
+        let first_id = generator.generate_id();
+        let second_id = generator.generate_id();
+        assert_eq!(first_id.byte_len(), 16);
+        assert!(first_id < second_id);
+        assert_ne!(
+            layout.value(&first_id, Field::Random),
+            layout.value(&second_id, Field::Random)
+        );
+    }
+
+    #[test]
+    fn test_checkpoint_survives_restart_with_clock_behind() {
+        let path = std::env::temp_dir().join(format!(
+            "rustflake-builder-checkpoint-{}",
+            std::process::id()
+        ));
+        let now = UNIX_EPOCH + Duration::from_millis(1_546_300_800_000);
+        let lease = Duration::from_secs(1);
+        let before = GeneratorBuilder::new()
+            .seed([1; 6])
+            .clock(ManualClock::new(now))
+            .checkpoint(FileCheckpoint::new(&path), lease)
+            .build()
+            .unwrap();
+        let issued = before.generate_id();
+
+        let after = GeneratorBuilder::new()
+            .seed([1; 6])
+            .clock(ManualClock::new(now - Duration::from_secs(5)))
+            .clock_rollback(ClockRollback::Fail(Duration::from_millis(1)))
+            .checkpoint(FileCheckpoint::new(&path), lease)
+            .build()
+            .unwrap();
+        let flake_id = after.try_generate_id().unwrap();
+        assert!(issued < flake_id);
+        assert_eq!(flake_id.timestamp(), now + lease);
+        assert_eq!(after.clock_skew(), Duration::from_millis(0));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_checkpoint_restart_within_lease_is_not_a_rollback() {
+        let path = std::env::temp_dir().join(format!(
+            "rustflake-builder-checkpoint-lease-{}",
+            std::process::id()
+        ));
+        let now = UNIX_EPOCH + Duration::from_millis(1_546_300_800_000);
+        let lease = Duration::from_secs(1);
+        let builder = |clock: ManualClock| {
+            GeneratorBuilder::new()
+                .seed([1; 6])
+                .clock(clock)
+                .clock_rollback(ClockRollback::Fail(Duration::from_millis(50)))
+                .checkpoint(FileCheckpoint::new(&path), lease)
+                .build()
+                .unwrap()
+        };
+        let issued = builder(ManualClock::new(now)).generate_id();
+
+        let clock = ManualClock::new(now + Duration::from_millis(100));
+        let after = builder(clock.clone());
+        let flake_id = after.try_generate_id().unwrap();
+        assert!(issued < flake_id);
+        assert_eq!(flake_id.timestamp(), now + lease);
+        assert_eq!(after.clock_skew(), Duration::from_millis(0));
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(after.generate_id().timestamp(), clock.now());
+        clock.rewind(Duration::from_millis(500));
+        assert!(matches!(
+            after.try_generate_id(),
+            Err(Error::ClockMovedBackwards(_))
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_checkpoint_with_micros_never_reissues_ids() {
+        let path = std::env::temp_dir().join(format!(
+            "rustflake-builder-checkpoint-micros-{}",
+            std::process::id()
+        ));
+        let epoch = UNIX_EPOCH + Duration::from_secs(1_546_300_800);
+        let now = epoch + Duration::from_micros(1_234_567);
+        let lease = Duration::from_micros(50);
+        let builder = |clock: ManualClock| {
+            GeneratorBuilder::new()
+                .seed([1; 6])
+                .epoch(epoch)
+                .time_unit(TimeUnit::Micros)
+                .clock(clock)
+                .checkpoint(FileCheckpoint::new(&path), lease)
+                .build()
+                .unwrap()
+        };
+        let issued = builder(ManualClock::new(now)).generate_id();
+        let after = builder(ManualClock::new(now - Duration::from_micros(10)));
+        assert!(issued < after.generate_id());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
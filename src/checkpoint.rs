@@ -0,0 +1,182 @@
+use std::fmt::Debug;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::clock::TimeUnit;
+use crate::error::Error;
+
+/// Durable storage for a generator's high-water mark: a time no id has been
+/// issued at or after. A generator built with a checkpoint never issues ids
+/// below the stored mark: until its clock passes the mark after a restart,
+/// ids take the mark's timestamp.
+pub trait Checkpoint: Debug + Send + Sync {
+    fn load(&self) -> Result<Option<SystemTime>, Error>;
+    fn store(&self, high_water: SystemTime) -> Result<(), Error>;
+}
+
+/// Keeps the high-water mark in a file as milliseconds since the Unix epoch,
+/// rounded up so the stored mark is never below the real one. Writes go to a
+/// temporary file that is synced and then renamed over the old one, so a
+/// crash leaves either the old mark or the new one.
+#[derive(Debug, Clone)]
+pub struct FileCheckpoint(PathBuf);
+
+impl FileCheckpoint {
+    pub fn new<P: Into<PathBuf>>(path: P) -> FileCheckpoint {
+        FileCheckpoint(path.into())
+    }
+}
+
+impl Checkpoint for FileCheckpoint {
+    fn load(&self) -> Result<Option<SystemTime>, Error> {
+        let contents = match fs::read_to_string(&self.0) {
+            Ok(contents) => contents,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Error::Io(e)),
+        };
+        let millis = contents.trim().parse::<u64>().map_err(|e| {
+            Error::Io(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid checkpoint {:?}: {}", self.0, e),
+            ))
+        })?;
+        Ok(Some(UNIX_EPOCH + Duration::from_millis(millis)))
+    }
+
+    fn store(&self, high_water: SystemTime) -> Result<(), Error> {
+        let millis = high_water
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| {
+                elapsed.as_millis() + u128::from(elapsed.subsec_nanos() % 1_000_000 != 0)
+            })
+            .unwrap_or(0);
+        let mut tmp = self.0.clone().into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        let mut file = File::create(&tmp).map_err(Error::Io)?;
+        file.write_all(millis.to_string().as_bytes())
+            .and_then(|()| file.sync_all())
+            .map_err(Error::Io)?;
+        fs::rename(&tmp, &self.0).map_err(Error::Io)?;
+        sync_parent(&self.0).map_err(Error::Io)
+    }
+}
+
+// Makes the rename itself durable.
+#[cfg(unix)]
+fn sync_parent(path: &std::path::Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => File::open(parent)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent(_path: &std::path::Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Leases timestamps from a checkpoint: ids may be issued below the stored
+/// mark, and the mark is pushed a lease ahead whenever a timestamp reaches
+/// it. Shared by every shard built from the same builder.
+#[derive(Debug)]
+pub(crate) struct Checkpointer {
+    checkpoint: Box<dyn Checkpoint>,
+    epoch: SystemTime,
+    time_unit: TimeUnit,
+    lease: u64,
+    lease_until: AtomicU64,
+    lock: Mutex<()>,
+}
+
+impl Checkpointer {
+    pub(crate) fn new(
+        checkpoint: Box<dyn Checkpoint>,
+        lease: Duration,
+        epoch: SystemTime,
+        time_unit: TimeUnit,
+    ) -> Checkpointer {
+        Checkpointer {
+            checkpoint,
+            epoch,
+            time_unit,
+            lease: time_unit.ticks(lease).max(1),
+            lease_until: AtomicU64::new(0),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// The stored mark in ticks since the epoch, or zero if there is none.
+    pub(crate) fn load(&self) -> Result<u64, Error> {
+        let high_water = self.checkpoint.load()?;
+        Ok(high_water.map_or(0, |high_water| {
+            self.time_unit.ticks_since(self.epoch, high_water)
+        }))
+    }
+
+    /// Makes sure ids at `timestamp` are covered by the stored mark, writing
+    /// a new one first if they aren't.
+    pub(crate) fn cover(&self, timestamp: u64) -> Result<(), Error> {
+        if timestamp < self.lease_until.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        if timestamp < self.lease_until.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        let lease_until = timestamp.saturating_add(self.lease);
+        self.checkpoint
+            .store(self.epoch + self.time_unit.duration(lease_until))?;
+        self.lease_until.store(lease_until, Ordering::Release);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_file_checkpoint_round_trip() {
+        let path = env::temp_dir().join(format!("rustflake-checkpoint-{}", std::process::id()));
+        let checkpoint = FileCheckpoint::new(&path);
+        assert!(checkpoint.load().unwrap().is_none());
+        let high_water = UNIX_EPOCH + Duration::from_millis(1_546_300_800_123);
+        checkpoint.store(high_water).unwrap();
+        assert_eq!(checkpoint.load().unwrap(), Some(high_water));
+        checkpoint
+            .store(high_water + Duration::from_micros(1))
+            .unwrap();
+        assert_eq!(
+            checkpoint.load().unwrap(),
+            Some(high_water + Duration::from_millis(1))
+        );
+        fs::write(&path, "garbage").unwrap();
+        assert!(matches!(checkpoint.load(), Err(Error::Io(_))));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_cover_leases_ahead() {
+        let path = env::temp_dir().join(format!("rustflake-lease-{}", std::process::id()));
+        let checkpointer = Checkpointer::new(
+            Box::new(FileCheckpoint::new(&path)),
+            Duration::from_millis(1_000),
+            UNIX_EPOCH,
+            TimeUnit::Millis,
+        );
+        checkpointer.cover(5).unwrap();
+        assert_eq!(checkpointer.load().unwrap(), 1_005);
+        checkpointer.cover(1_004).unwrap();
+        assert_eq!(checkpointer.load().unwrap(), 1_005);
+        checkpointer.cover(1_005).unwrap();
+        assert_eq!(checkpointer.load().unwrap(), 2_005);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,142 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// The length of one timestamp tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TimeUnit {
+    #[default]
+    Millis,
+    /// Ten milliseconds, as used by Sonyflake.
+    TenMillis,
+    Micros,
+}
+
+impl TimeUnit {
+    pub fn ticks(self, elapsed: Duration) -> u64 {
+        match self {
+            TimeUnit::Millis => elapsed.as_millis() as u64,
+            TimeUnit::TenMillis => (elapsed.as_millis() / 10) as u64,
+            TimeUnit::Micros => elapsed.as_micros() as u64,
+        }
+    }
+
+    pub fn duration(self, ticks: u64) -> Duration {
+        match self {
+            TimeUnit::Millis => Duration::from_millis(ticks),
+            TimeUnit::TenMillis => Duration::from_millis(ticks) * 10,
+            TimeUnit::Micros => Duration::from_micros(ticks),
+        }
+    }
+
+    /// The number of whole ticks from `epoch` to `now`, or zero if `now` is
+    /// before `epoch`.
+    pub fn ticks_since(self, epoch: SystemTime, now: SystemTime) -> u64 {
+        now.duration_since(epoch)
+            .map(|elapsed| self.ticks(elapsed))
+            .unwrap_or(0)
+    }
+}
+
+/// What a generator does when its clock reads earlier than the latest
+/// timestamp it has already issued, e.g. after an NTP step.
+#[derive(Clone, Default)]
+pub enum ClockRollback {
+    /// Keep issuing ids with the latest timestamp until the clock catches up.
+    #[default]
+    KeepLast,
+    /// Sleep until the clock catches up.
+    Block,
+    /// Keep the latest timestamp while the skew is within the threshold,
+    /// fail with `Error::ClockMovedBackwards` beyond it.
+    Fail(Duration),
+    /// Keep the latest timestamp and report the skew once for each id or
+    /// batch generated while the clock is behind.
+    Notify(Arc<dyn Fn(Duration) + Send + Sync>),
+}
+
+impl fmt::Debug for ClockRollback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ClockRollback::KeepLast => f.write_str("KeepLast"),
+            ClockRollback::Block => f.write_str("Block"),
+            ClockRollback::Fail(threshold) => f.debug_tuple("Fail").field(&threshold).finish(),
+            ClockRollback::Notify(_) => f.write_str("Notify(..)"),
+        }
+    }
+}
+
+/// The source of the current time for a generator.
+pub trait Clock: fmt::Debug + Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that only moves when told to. Clones share the same time, so a
+/// test can keep one handle and give another to a generator.
+#[derive(Debug, Clone)]
+pub struct ManualClock(Arc<Mutex<SystemTime>>);
+
+impl ManualClock {
+    pub fn new(now: SystemTime) -> ManualClock {
+        ManualClock(Arc::new(Mutex::new(now)))
+    }
+
+    pub fn set(&self, now: SystemTime) {
+        *self.0.lock().unwrap() = now;
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.0.lock().unwrap() += duration;
+    }
+
+    pub fn rewind(&self, duration: Duration) {
+        *self.0.lock().unwrap() -= duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        *self.0.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn test_time_unit_ticks() {
+        let elapsed = Duration::from_micros(123_456);
+        assert_eq!(TimeUnit::Millis.ticks(elapsed), 123);
+        assert_eq!(TimeUnit::TenMillis.ticks(elapsed), 12);
+        assert_eq!(TimeUnit::Micros.ticks(elapsed), 123_456);
+        assert_eq!(TimeUnit::TenMillis.duration(12), Duration::from_millis(120));
+        assert_eq!(
+            TimeUnit::Millis.ticks_since(UNIX_EPOCH + elapsed, UNIX_EPOCH),
+            0
+        );
+    }
+
+    #[test]
+    fn test_manual_clock_clones_share_time() {
+        let clock = ManualClock::new(UNIX_EPOCH);
+        let handle = clock.clone();
+        handle.advance(Duration::from_millis(5));
+        assert_eq!(clock.now(), UNIX_EPOCH + Duration::from_millis(5));
+        handle.rewind(Duration::from_millis(2));
+        assert_eq!(clock.now(), UNIX_EPOCH + Duration::from_millis(3));
+        handle.set(UNIX_EPOCH);
+        assert_eq!(clock.now(), UNIX_EPOCH);
+    }
+}
@@ -0,0 +1,416 @@
+use std::hint;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::clock::{Clock, SystemClock, TimeUnit};
+use crate::error::Error;
+use crate::get_non_loopback_address;
+use crate::seed::{MacAddressSeed, SeedProvider};
+
+/// Twitter's snowflake epoch, 2010-11-04T01:42:54.657Z, in milliseconds.
+pub const TWITTER_EPOCH_MS: u64 = 1_288_834_974_657;
+
+/// How the 63 usable bits of a compact id are split. The sign bit is always
+/// left clear so ids fit a signed `BIGINT` column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactLayout {
+    timestamp_bits: u8,
+    worker_bits: u8,
+    sequence_bits: u8,
+}
+
+impl CompactLayout {
+    pub fn new(
+        timestamp_bits: u8,
+        worker_bits: u8,
+        sequence_bits: u8,
+    ) -> Result<CompactLayout, Error> {
+        if u32::from(timestamp_bits) + u32::from(worker_bits) + u32::from(sequence_bits) > 63 {
+            return Err(Error::InvalidLayout(
+                "timestamp, worker and sequence bits exceed 63",
+            ));
+        }
+        if timestamp_bits == 0 || sequence_bits == 0 {
+            return Err(Error::InvalidLayout(
+                "timestamp and sequence need at least one bit",
+            ));
+        }
+        Ok(CompactLayout {
+            timestamp_bits,
+            worker_bits,
+            sequence_bits,
+        })
+    }
+
+    pub fn timestamp_bits(&self) -> u8 {
+        self.timestamp_bits
+    }
+
+    pub fn worker_bits(&self) -> u8 {
+        self.worker_bits
+    }
+
+    pub fn sequence_bits(&self) -> u8 {
+        self.sequence_bits
+    }
+
+    pub fn max_worker_id(&self) -> u64 {
+        mask(self.worker_bits)
+    }
+
+    fn max_sequence(&self) -> u64 {
+        mask(self.sequence_bits)
+    }
+}
+
+impl Default for CompactLayout {
+    fn default() -> CompactLayout {
+        CompactLayout {
+            timestamp_bits: 41,
+            worker_bits: 10,
+            sequence_bits: 12,
+        }
+    }
+}
+
+/// The parts a `CompactGenerator` combined to mint a compact id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactComponents {
+    pub timestamp: SystemTime,
+    pub worker_id: u64,
+    pub sequence: u64,
+}
+
+/// Generates Twitter-style 64-bit ids: ticks since a custom epoch, a worker
+/// id and a sequence that restarts every tick. Ticks are milliseconds unless
+/// configured otherwise.
+#[derive(Debug)]
+pub struct CompactGenerator {
+    layout: CompactLayout,
+    epoch: SystemTime,
+    time_unit: TimeUnit,
+    worker_id: u64,
+    state: AtomicU64,
+    clock: Box<dyn Clock>,
+}
+
+impl PartialEq for CompactGenerator {
+    fn eq(&self, other: &CompactGenerator) -> bool {
+        self.layout == other.layout
+            && self.epoch == other.epoch
+            && self.time_unit == other.time_unit
+            && self.worker_id == other.worker_id
+            && self.state.load(Ordering::SeqCst) == other.state.load(Ordering::SeqCst)
+    }
+}
+
+impl CompactGenerator {
+    pub fn new() -> CompactGenerator {
+        CompactGenerator::with_seed(get_non_loopback_address(), CompactLayout::default())
+    }
+
+    pub fn try_new() -> Result<CompactGenerator, Error> {
+        CompactGenerator::with_provider(&MacAddressSeed, CompactLayout::default())
+    }
+
+    pub fn with_provider<P: SeedProvider>(
+        provider: &P,
+        layout: CompactLayout,
+    ) -> Result<CompactGenerator, Error> {
+        provider
+            .seed()
+            .map(|seed| CompactGenerator::with_seed(seed, layout))
+    }
+
+    pub fn with_seed(seed: [u8; 6], layout: CompactLayout) -> CompactGenerator {
+        CompactGenerator {
+            layout,
+            epoch: UNIX_EPOCH + Duration::from_millis(TWITTER_EPOCH_MS),
+            time_unit: TimeUnit::Millis,
+            worker_id: crate::fold_seed(seed, layout.worker_bits),
+            state: AtomicU64::new(0),
+            clock: Box::new(SystemClock),
+        }
+    }
+
+    pub fn with_worker_id(
+        worker_id: u64,
+        layout: CompactLayout,
+    ) -> Result<CompactGenerator, Error> {
+        if worker_id > layout.max_worker_id() {
+            return Err(Error::WorkerIdOutOfRange(worker_id));
+        }
+        Ok(CompactGenerator {
+            layout,
+            epoch: UNIX_EPOCH + Duration::from_millis(TWITTER_EPOCH_MS),
+            time_unit: TimeUnit::Millis,
+            worker_id,
+            state: AtomicU64::new(0),
+            clock: Box::new(SystemClock),
+        })
+    }
+
+    pub fn with_epoch(mut self, epoch: SystemTime) -> CompactGenerator {
+        self.epoch = epoch;
+        self
+    }
+
+    pub fn with_time_unit(mut self, time_unit: TimeUnit) -> CompactGenerator {
+        self.time_unit = time_unit;
+        self
+    }
+
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> CompactGenerator {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    pub fn layout(&self) -> CompactLayout {
+        self.layout
+    }
+
+    pub fn worker_id(&self) -> u64 {
+        self.worker_id
+    }
+
+    pub fn epoch(&self) -> SystemTime {
+        self.epoch
+    }
+
+    pub fn time_unit(&self) -> TimeUnit {
+        self.time_unit
+    }
+
+    /// The current time according to the generator's clock.
+    pub fn now(&self) -> SystemTime {
+        self.clock.now()
+    }
+
+    pub fn generate(&self) -> u64 {
+        match self.try_generate() {
+            Ok(id) => id,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Fails with `Error::TimestampOverflow` once the time since the epoch no
+    /// longer fits the layout, and with `Error::ClockMovedBackwards` if a
+    /// tick's sequence runs out while the clock is behind that tick.
+    pub fn try_generate(&self) -> Result<u64, Error> {
+        let sequence_bits = self.layout.sequence_bits;
+        let max_sequence = self.layout.max_sequence();
+        let mut current = self.state.load(Ordering::SeqCst);
+        loop {
+            let now = self.elapsed_ticks()?;
+            let last = current >> sequence_bits;
+            let next = if now > last {
+                now << sequence_bits
+            } else if current & max_sequence < max_sequence {
+                current + 1
+            } else if now < last {
+                return Err(Error::ClockMovedBackwards(
+                    self.time_unit.duration(last - now),
+                ));
+            } else {
+                // Sequence exhausted for this tick, wait for the next one.
+                hint::spin_loop();
+                current = self.state.load(Ordering::SeqCst);
+                continue;
+            };
+            match self.state.compare_exchange_weak(
+                current,
+                next,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => return Ok(self.compose(next)),
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    pub fn decode(&self, id: u64) -> CompactComponents {
+        let sequence_bits = self.layout.sequence_bits;
+        let worker_shift = sequence_bits + self.layout.worker_bits;
+        CompactComponents {
+            timestamp: self.epoch + self.time_unit.duration(id >> worker_shift),
+            worker_id: (id >> sequence_bits) & self.layout.max_worker_id(),
+            sequence: id & self.layout.max_sequence(),
+        }
+    }
+
+    fn elapsed_ticks(&self) -> Result<u64, Error> {
+        let elapsed = self.time_unit.ticks_since(self.epoch, self.clock.now());
+        if elapsed > mask(self.layout.timestamp_bits) {
+            return Err(Error::TimestampOverflow);
+        }
+        Ok(elapsed)
+    }
+
+    fn compose(&self, state: u64) -> u64 {
+        let sequence_bits = self.layout.sequence_bits;
+        let timestamp = state >> sequence_bits;
+        let sequence = state & self.layout.max_sequence();
+        (timestamp << (sequence_bits + self.layout.worker_bits))
+            | (self.worker_id << sequence_bits)
+            | sequence
+    }
+}
+
+impl Default for CompactGenerator {
+    fn default() -> CompactGenerator {
+        CompactGenerator::new()
+    }
+}
+
+fn mask(bits: u8) -> u64 {
+    (1 << bits) - 1
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::clock::ManualClock;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_default_layout_is_twitter() {
+        let layout = CompactLayout::default();
+        assert_eq!(layout, CompactLayout::new(41, 10, 12).unwrap());
+        assert_eq!(layout.max_worker_id(), 1023);
+    }
+
+    #[test]
+    fn test_layout_rejects_more_than_63_bits() {
+        assert!(CompactLayout::new(42, 10, 12).is_err());
+        assert!(CompactLayout::new(41, 10, 0).is_err());
+    }
+
+    #[test]
+    fn test_worker_id_out_of_range() {
+        let layout = CompactLayout::new(41, 5, 12).unwrap();
+        assert!(CompactGenerator::with_worker_id(31, layout).is_ok());
+        assert!(matches!(
+            CompactGenerator::with_worker_id(32, layout),
+            Err(Error::WorkerIdOutOfRange(32))
+        ));
+    }
+
+    #[test]
+    fn test_worker_id_from_seed_fits_layout() {
+        let layout = CompactLayout::new(41, 7, 12).unwrap();
+        let generator = CompactGenerator::with_seed([0xff; 6], layout);
+        assert!(generator.worker_id() <= layout.max_worker_id());
+        assert_ne!(
+            CompactGenerator::with_seed([0, 0, 0, 0, 0, 1], layout).worker_id(),
+            CompactGenerator::with_seed([0, 0, 0, 0, 0, 2], layout).worker_id()
+        );
+    }
+
+    #[test]
+    fn test_generate_decodes_to_components() {
+        let generator = CompactGenerator::with_worker_id(513, CompactLayout::default()).unwrap();
+        let before = SystemTime::now() - Duration::from_millis(1);
+        let components = generator.decode(generator.generate());
+        let after = SystemTime::now();
+        assert_eq!(components.worker_id, 513);
+        assert_eq!(components.sequence, 0);
+        assert!(components.timestamp > before && components.timestamp <= after);
+    }
+
+    #[test]
+    fn test_generate_fits_signed_bigint() {
+        let generator = CompactGenerator::with_worker_id(1023, CompactLayout::default()).unwrap();
+        assert!(generator.generate() <= i64::MAX as u64);
+    }
+
+    #[test]
+    fn test_custom_epoch() {
+        let epoch = SystemTime::now() - Duration::from_secs(60);
+        let generator = CompactGenerator::with_worker_id(0, CompactLayout::default())
+            .unwrap()
+            .with_epoch(epoch);
+        let id = generator.generate();
+        let elapsed_ms = id >> 22;
+        assert!((60_000..61_000).contains(&elapsed_ms));
+    }
+
+    #[test]
+    fn test_subsequent_generate_calls_produce_increasing_values() {
+        let layout = CompactLayout::new(41, 10, 4).unwrap();
+        let generator = CompactGenerator::with_worker_id(7, layout).unwrap();
+        let mut set = HashSet::new();
+        let mut previous = generator.generate();
+        set.insert(previous);
+        for _x in 0..1000 {
+            let next = generator.generate();
+            assert!(next > previous);
+            assert!(set.insert(next));
+            previous = next;
+        }
+    }
+
+    #[test]
+    fn test_sequence_resets_each_millisecond() {
+        let clock = ManualClock::new(UNIX_EPOCH + Duration::from_millis(TWITTER_EPOCH_MS + 10));
+        let generator = CompactGenerator::with_worker_id(3, CompactLayout::default())
+            .unwrap()
+            .with_clock(clock.clone());
+        assert_eq!(generator.decode(generator.generate()).sequence, 0);
+        assert_eq!(generator.decode(generator.generate()).sequence, 1);
+        clock.advance(Duration::from_millis(1));
+        let components = generator.decode(generator.generate());
+        assert_eq!(components.sequence, 0);
+        assert_eq!(components.timestamp, clock.now());
+        assert_eq!(generator.generate() >> 22, 11);
+    }
+
+    #[test]
+    fn test_sonyflake_style_ten_millisecond_ticks() {
+        let epoch = UNIX_EPOCH + Duration::from_secs(1_409_529_600);
+        let clock = ManualClock::new(epoch + Duration::from_millis(12_345));
+        let layout = CompactLayout::new(39, 16, 8).unwrap();
+        let generator = CompactGenerator::with_worker_id(0xbeef, layout)
+            .unwrap()
+            .with_epoch(epoch)
+            .with_time_unit(TimeUnit::TenMillis)
+            .with_clock(clock);
+        let id = generator.generate();
+        assert_eq!(id >> 24, 1_234);
+        let components = generator.decode(id);
+        assert_eq!(components.timestamp, epoch + Duration::from_millis(12_340));
+        assert_eq!(components.worker_id, 0xbeef);
+    }
+
+    #[test]
+    fn test_try_generate_timestamp_overflow() {
+        let layout = CompactLayout::new(20, 10, 12).unwrap();
+        let generator = CompactGenerator::with_worker_id(1, layout).unwrap();
+        assert!(matches!(
+            generator.try_generate(),
+            Err(Error::TimestampOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_try_generate_fails_when_exhausted_behind_the_clock() {
+        let clock = ManualClock::new(UNIX_EPOCH + Duration::from_millis(TWITTER_EPOCH_MS + 10));
+        let layout = CompactLayout::new(41, 10, 1).unwrap();
+        let generator = CompactGenerator::with_worker_id(3, layout)
+            .unwrap()
+            .with_clock(clock.clone());
+        generator.generate();
+        clock.rewind(Duration::from_millis(5));
+        assert_eq!(generator.decode(generator.generate()).sequence, 1);
+        match generator.try_generate() {
+            Err(Error::ClockMovedBackwards(skew)) => assert_eq!(skew, Duration::from_millis(5)),
+            other => panic!("Expected ClockMovedBackwards, got {:?}", other),
+        }
+        clock.advance(Duration::from_millis(6));
+        assert_eq!(
+            generator.decode(generator.try_generate().unwrap()).sequence,
+            0
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::clock::TimeUnit;
+use crate::encoding::Encoding;
+use crate::error::DecodeError;
+use crate::flake_id::{Components, FlakeId};
+use crate::layout::{Field, Layout};
+
+/// Turns ids back into their components using the layout, epoch, time unit
+/// and encoding they were generated with. `Generator::decoder` returns one that
+/// matches the generator; the default matches `SnowFlaker::new`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decoder {
+    epoch: SystemTime,
+    time_unit: TimeUnit,
+    encoding: Encoding,
+    layout: Layout,
+}
+
+impl Decoder {
+    pub fn new(epoch: SystemTime, time_unit: TimeUnit, encoding: Encoding) -> Decoder {
+        Decoder {
+            epoch,
+            time_unit,
+            encoding,
+            layout: Layout::default(),
+        }
+    }
+
+    pub fn with_layout(mut self, layout: Layout) -> Decoder {
+        self.layout = layout;
+        self
+    }
+
+    pub fn epoch(&self) -> SystemTime {
+        self.epoch
+    }
+
+    pub fn time_unit(&self) -> TimeUnit {
+        self.time_unit
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Panics if the timestamp is later than `SystemTime` can represent,
+    /// which only a wide timestamp field can reach; see `try_components`.
+    pub fn components(&self, flake_id: &FlakeId) -> Components {
+        match self.try_components(flake_id) {
+            Ok(components) => components,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    pub fn try_components(&self, flake_id: &FlakeId) -> Result<Components, DecodeError> {
+        let ticks = self.layout.value(flake_id, Field::Timestamp);
+        let timestamp = self
+            .epoch
+            .checked_add(self.time_unit.duration(ticks))
+            .ok_or(DecodeError::TimestampOutOfRange)?;
+        let mut seed = [0; 6];
+        crate::put_uint(&mut seed, self.layout.value(flake_id, Field::Node), 0, 6);
+        Ok(Components {
+            timestamp,
+            seed,
+            sequence: self.layout.value(flake_id, Field::Sequence) as u32,
+            shard: self.layout.value(flake_id, Field::Shard) as u32,
+            random: self.layout.value(flake_id, Field::Random),
+        })
+    }
+
+    /// Decodes an encoded id, checking its length matches the layout and its
+    /// timestamp is representable.
+    pub fn decode(&self, id: &str) -> Result<Components, DecodeError> {
+        let flake_id = FlakeId::decode(id, self.encoding)?;
+        if flake_id.byte_len() != self.layout.byte_len() {
+            return Err(DecodeError::InvalidLength(id.len()));
+        }
+        self.try_components(&flake_id)
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Decoder {
+        Decoder::new(UNIX_EPOCH, TimeUnit::Millis, Encoding::Base64Url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::FLAKE_ID_LEN;
+    use std::time::Duration;
+
+    #[test]
+    fn test_default_matches_flake_id_components() {
+        let flake_id = FlakeId::from_bytes([0x21; FLAKE_ID_LEN]);
+        assert_eq!(
+            Decoder::default().components(&flake_id),
+            flake_id.components()
+        );
+        assert_eq!(
+            Decoder::default().decode(&flake_id.to_string()),
+            Ok(flake_id.components())
+        );
+    }
+
+    #[test]
+    fn test_custom_layout() {
+        let layout = Layout::builder()
+            .reserved(1)
+            .timestamp(41)
+            .node(10)
+            .sequence(12)
+            .build()
+            .unwrap();
+        let decoder = Decoder::default().with_layout(layout);
+        let flake_id = FlakeId::from_slice(&[0, 0, 0, 0, 0, 0xc0, 0x30, 0x05]).unwrap();
+        let components = decoder.decode(&flake_id.to_string()).unwrap();
+        assert_eq!(components.timestamp, UNIX_EPOCH + Duration::from_millis(3));
+        assert_eq!(components.seed, [0, 0, 0, 0, 0, 3]);
+        assert_eq!(components.sequence, 5);
+        assert_eq!(
+            decoder.decode(&FlakeId::from_bytes([0; FLAKE_ID_LEN]).to_string()),
+            Err(DecodeError::InvalidLength(20))
+        );
+    }
+
+    #[test]
+    fn test_custom_epoch_and_encoding() {
+        let epoch = UNIX_EPOCH + Duration::from_secs(1_546_300_800);
+        let decoder = Decoder::new(epoch, TimeUnit::TenMillis, Encoding::Hex);
+        let components = decoder.decode("000000000064010203040506000007").unwrap();
+        assert_eq!(components.timestamp, epoch + Duration::from_secs(1));
+        assert_eq!(components.seed, [1, 2, 3, 4, 5, 6]);
+        assert_eq!(components.sequence, 7);
+        assert_eq!(
+            decoder.decode(&FlakeId::from_bytes([0; FLAKE_ID_LEN]).to_string()),
+            Err(DecodeError::InvalidLength(20))
+        );
+    }
+
+    #[test]
+    fn test_wide_timestamps_do_not_overflow() {
+        let layout = Layout::builder()
+            .timestamp(64)
+            .node(48)
+            .sequence(16)
+            .build()
+            .unwrap();
+        let id = FlakeId::from_slice(&[0xff; 16]).unwrap().to_string();
+        let decoder =
+            Decoder::new(UNIX_EPOCH, TimeUnit::TenMillis, Encoding::Base64Url).with_layout(layout);
+        assert_eq!(
+            decoder.decode(&id).unwrap().timestamp,
+            UNIX_EPOCH + Duration::from_millis(u64::MAX) * 10
+        );
+        let late_epoch = UNIX_EPOCH + Duration::from_secs(i64::MAX as u64 - 1_000);
+        let decoder =
+            Decoder::new(late_epoch, TimeUnit::Millis, Encoding::Base64Url).with_layout(layout);
+        assert_eq!(decoder.decode(&id), Err(DecodeError::TimestampOutOfRange));
+    }
+}
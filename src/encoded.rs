@@ -0,0 +1,170 @@
+//! Serde support, behind the `serde` feature.
+//!
+//! Human-readable formats such as JSON see a `FlakeId` as a string in the
+//! default `Base64Url` encoding; binary formats see its raw bytes. Either
+//! form is validated when deserializing. The modules here pick another
+//! encoding for a field with `#[serde(with = "rustflake::encoded::hex")]`.
+
+use std::fmt;
+
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+
+use crate::encoding::Encoding;
+use crate::flake_id::{FlakeId, MAX_FLAKE_ID_LEN};
+
+impl Serialize for FlakeId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize(self, Encoding::Base64Url, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for FlakeId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<FlakeId, D::Error> {
+        deserialize(deserializer, Encoding::Base64Url)
+    }
+}
+
+fn serialize<S: Serializer>(
+    flake_id: &FlakeId,
+    encoding: Encoding,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        serializer.serialize_str(&flake_id.encode_str(encoding))
+    } else {
+        serializer.serialize_bytes(flake_id.as_bytes())
+    }
+}
+
+fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+    encoding: Encoding,
+) -> Result<FlakeId, D::Error> {
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_str(FlakeIdVisitor(encoding))
+    } else {
+        deserializer.deserialize_bytes(FlakeIdVisitor(encoding))
+    }
+}
+
+struct FlakeIdVisitor(Encoding);
+
+impl<'de> Visitor<'de> for FlakeIdVisitor {
+    type Value = FlakeId;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an encoded flake id or its raw bytes")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<FlakeId, E> {
+        FlakeId::decode(value, self.0).map_err(E::custom)
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<FlakeId, E> {
+        FlakeId::from_slice(value).map_err(E::custom)
+    }
+
+    // Some formats hand raw bytes over as a sequence of integers.
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<FlakeId, A::Error> {
+        let mut bytes = [0; MAX_FLAKE_ID_LEN];
+        let mut len = 0;
+        while let Some(byte) = seq.next_element()? {
+            if len == MAX_FLAKE_ID_LEN {
+                return Err(de::Error::invalid_length(len + 1, &self));
+            }
+            bytes[len] = byte;
+            len += 1;
+        }
+        FlakeId::from_slice(&bytes[..len]).map_err(de::Error::custom)
+    }
+}
+
+macro_rules! encoded_with {
+    ($name:ident, $encoding:expr) => {
+        pub mod $name {
+            use super::*;
+
+            pub fn serialize<S: Serializer>(
+                flake_id: &FlakeId,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                super::serialize(flake_id, $encoding, serializer)
+            }
+
+            pub fn deserialize<'de, D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<FlakeId, D::Error> {
+                super::deserialize(deserializer, $encoding)
+            }
+        }
+    };
+}
+
+encoded_with!(base64_url, Encoding::Base64Url);
+encoded_with!(base64_sortable, Encoding::Base64Sortable);
+encoded_with!(base32_crockford, Encoding::Base32Crockford);
+encoded_with!(hex, Encoding::Hex);
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Row {
+        id: FlakeId,
+        #[serde(with = "crate::encoded::hex")]
+        parent: FlakeId,
+    }
+
+    fn row() -> Row {
+        Row {
+            id: FlakeId::from_bytes([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]),
+            parent: FlakeId::from_slice(&[0xab; 8]).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_json_uses_encoded_strings() {
+        let json = serde_json::to_string(&row()).unwrap();
+        assert_eq!(
+            json,
+            r#"{"id":"AQIDBAUGBwgJCgsMDQ4P","parent":"abababababababab"}"#
+        );
+        assert_eq!(serde_json::from_str::<Row>(&json).unwrap(), row());
+    }
+
+    #[test]
+    fn test_json_rejects_invalid_ids() {
+        assert!(serde_json::from_str::<FlakeId>(r#""AQIDBAUGBwgJCgsMDQ4""#).is_err());
+        assert!(serde_json::from_str::<FlakeId>(r#""AQIDBAUGBwgJCgsMDQ4*""#).is_err());
+        assert!(serde_json::from_str::<FlakeId>("[1, 2, 3]").is_err());
+    }
+
+    #[test]
+    fn test_bincode_uses_raw_bytes() {
+        let flake_id = row().id;
+        let encoded = bincode::serialize(&flake_id).unwrap();
+        assert_eq!(encoded.len(), 8 + 15);
+        assert_eq!(&encoded[8..], flake_id.as_bytes());
+        assert_eq!(
+            bincode::deserialize::<Row>(&bincode::serialize(&row()).unwrap()).unwrap(),
+            row()
+        );
+        let mut truncated = encoded.clone();
+        truncated[0] = 14;
+        truncated.pop();
+        assert!(bincode::deserialize::<FlakeId>(&truncated).is_err());
+    }
+
+    #[test]
+    fn test_message_pack_round_trip() {
+        let encoded = rmp_serde::to_vec(&row()).unwrap();
+        assert_eq!(rmp_serde::from_slice::<Row>(&encoded).unwrap(), row());
+        let encoded = rmp_serde::to_vec(&row().id).unwrap();
+        assert_eq!(encoded.len(), 2 + 15);
+    }
+}
@@ -0,0 +1,354 @@
+use crate::error::DecodeError;
+
+const BASE64_SORTABLE_ALPHABET: &[u8; 64] =
+    b"-0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ_abcdefghijklmnopqrstuvwxyz";
+
+const BASE32_CROCKFORD_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+const HEX_ALPHABET: &[u8; 16] = b"0123456789abcdef";
+
+const INVALID: u8 = 0xff;
+
+const BASE64_SORTABLE_VALUES: [u8; 256] = values(BASE64_SORTABLE_ALPHABET);
+
+const BASE32_CROCKFORD_VALUES: [u8; 256] = crockford_values();
+
+const HEX_VALUES: [u8; 256] = case_insensitive(values(HEX_ALPHABET));
+
+/// String encodings for flake ids.
+///
+/// `Base64Url` is the URL-safe base64 produced by `SnowFlaker::generate`. Its
+/// alphabet is not in ASCII order, so comparing two encoded ids does not
+/// always agree with comparing the ids themselves. `Base64Sortable` uses the
+/// same 64 URL-safe characters arranged in ASCII order, so for ids of equal
+/// length string order is byte order.
+///
+/// `Base32Crockford` (upper case) and `Hex` (lower case) are for consumers
+/// that treat ids case-insensitively; both decode either case and both keep
+/// string order equal to byte order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Encoding {
+    #[default]
+    Base64Url,
+    Base64Sortable,
+    Base32Crockford,
+    Hex,
+}
+
+impl Encoding {
+    /// Looks an encoding up by the name `name` returns, case-insensitively.
+    pub fn from_name(name: &str) -> Option<Encoding> {
+        [
+            Encoding::Base64Url,
+            Encoding::Base64Sortable,
+            Encoding::Base32Crockford,
+            Encoding::Hex,
+        ]
+        .iter()
+        .cloned()
+        .find(|encoding| encoding.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Base64Url => "base64url",
+            Encoding::Base64Sortable => "base64sortable",
+            Encoding::Base32Crockford => "base32",
+            Encoding::Hex => "hex",
+        }
+    }
+
+    pub fn encode(self, bytes: &[u8]) -> String {
+        let mut encoded = vec![0; self.encoded_len(bytes.len())];
+        self.encode_into(bytes, &mut encoded);
+        String::from_utf8(encoded).expect("alphabets are ASCII")
+    }
+
+    /// Encodes `bytes` into the start of `out` without allocating, returning
+    /// the number of bytes written. Panics if `out` is shorter than
+    /// `encoded_len(bytes.len())`.
+    pub fn encode_into(self, bytes: &[u8], out: &mut [u8]) -> usize {
+        let out = &mut out[..self.encoded_len(bytes.len())];
+        match self {
+            Encoding::Base64Url => base64::encode_config_slice(bytes, base64::URL_SAFE_NO_PAD, out),
+            Encoding::Base64Sortable => encode_bits(bytes, BASE64_SORTABLE_ALPHABET, 6, out),
+            Encoding::Base32Crockford => encode_bits(bytes, BASE32_CROCKFORD_ALPHABET, 5, out),
+            Encoding::Hex => encode_bits(bytes, HEX_ALPHABET, 4, out),
+        }
+    }
+
+    pub fn decode(self, encoded: &str, bytes: &mut [u8]) -> Result<(), DecodeError> {
+        if encoded.len() != self.encoded_len(bytes.len()) {
+            return Err(DecodeError::InvalidLength(encoded.len()));
+        }
+        match self {
+            Encoding::Base64Url => {
+                let decoded = base64::decode_config(encoded, base64::URL_SAFE_NO_PAD).map_err(
+                    |e| match e {
+                        base64::DecodeError::InvalidByte(index, byte)
+                        | base64::DecodeError::InvalidLastSymbol(index, byte) => {
+                            DecodeError::InvalidCharacter(index, byte)
+                        }
+                        base64::DecodeError::InvalidLength => {
+                            DecodeError::InvalidLength(encoded.len())
+                        }
+                    },
+                )?;
+                bytes.copy_from_slice(&decoded);
+                Ok(())
+            }
+            Encoding::Base64Sortable => decode_bits(encoded, &BASE64_SORTABLE_VALUES, 6, bytes),
+            Encoding::Base32Crockford => decode_bits(encoded, &BASE32_CROCKFORD_VALUES, 5, bytes),
+            Encoding::Hex => decode_bits(encoded, &HEX_VALUES, 4, bytes),
+        }
+    }
+
+    pub fn encoded_len(self, byte_len: usize) -> usize {
+        match self {
+            Encoding::Base64Url | Encoding::Base64Sortable => (byte_len * 8).div_ceil(6),
+            Encoding::Base32Crockford => (byte_len * 8).div_ceil(5),
+            Encoding::Hex => byte_len * 2,
+        }
+    }
+}
+
+const fn values(alphabet: &[u8]) -> [u8; 256] {
+    let mut values = [INVALID; 256];
+    let mut i = 0;
+    while i < alphabet.len() {
+        values[alphabet[i] as usize] = i as u8;
+        i += 1;
+    }
+    values
+}
+
+const fn case_insensitive(mut values: [u8; 256]) -> [u8; 256] {
+    let mut c = b'A';
+    while c <= b'Z' {
+        let upper = values[c as usize];
+        let lower = values[c.to_ascii_lowercase() as usize];
+        if upper == INVALID {
+            values[c as usize] = lower;
+        } else if lower == INVALID {
+            values[c.to_ascii_lowercase() as usize] = upper;
+        }
+        c += 1;
+    }
+    values
+}
+
+const fn crockford_values() -> [u8; 256] {
+    let mut values = values(BASE32_CROCKFORD_ALPHABET);
+    values[b'O' as usize] = 0;
+    values[b'I' as usize] = 1;
+    values[b'L' as usize] = 1;
+    case_insensitive(values)
+}
+
+fn encode_bits(bytes: &[u8], alphabet: &[u8], bits_per_char: u32, out: &mut [u8]) -> usize {
+    let mask = (1 << bits_per_char) - 1;
+    let mut written = 0;
+    let mut buffer: u32 = 0;
+    let mut buffered_bits = 0;
+    for &byte in bytes {
+        buffer = (buffer << 8) | u32::from(byte);
+        buffered_bits += 8;
+        while buffered_bits >= bits_per_char {
+            buffered_bits -= bits_per_char;
+            out[written] = alphabet[((buffer >> buffered_bits) & mask) as usize];
+            written += 1;
+        }
+    }
+    if buffered_bits > 0 {
+        let index = (buffer << (bits_per_char - buffered_bits)) & mask;
+        out[written] = alphabet[index as usize];
+        written += 1;
+    }
+    written
+}
+
+fn decode_bits(
+    encoded: &str,
+    values: &[u8; 256],
+    bits_per_char: u32,
+    bytes: &mut [u8],
+) -> Result<(), DecodeError> {
+    let mut buffer: u32 = 0;
+    let mut buffered_bits = 0;
+    let mut written = 0;
+    for (index, &c) in encoded.as_bytes().iter().enumerate() {
+        let value = values[c as usize];
+        if value == INVALID {
+            return Err(DecodeError::InvalidCharacter(index, c));
+        }
+        buffer = (buffer << bits_per_char) | u32::from(value);
+        buffered_bits += bits_per_char;
+        if buffered_bits >= 8 {
+            buffered_bits -= 8;
+            bytes[written] = (buffer >> buffered_bits) as u8;
+            written += 1;
+        }
+        buffer &= (1 << buffered_bits) - 1;
+    }
+    if buffer != 0 {
+        return Err(DecodeError::InvalidCharacter(
+            encoded.len() - 1,
+            encoded.as_bytes()[encoded.len() - 1],
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::FLAKE_ID_LEN;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(Encoding::from_name("HEX"), Some(Encoding::Hex));
+        assert_eq!(
+            Encoding::from_name(Encoding::Base32Crockford.name()),
+            Some(Encoding::Base32Crockford)
+        );
+        assert_eq!(Encoding::from_name("base58"), None);
+    }
+
+    #[test]
+    fn test_sortable_alphabet_is_ascending() {
+        assert!(BASE64_SORTABLE_ALPHABET.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_url_safe_alphabet_is_not_ascending() {
+        let low = [0xcc; FLAKE_ID_LEN];
+        let mut high = low;
+        high[0] = 0xd0;
+        assert!(low < high);
+        assert!(Encoding::Base64Url.encode(&low) > Encoding::Base64Url.encode(&high));
+    }
+
+    #[test]
+    fn test_sortable_encoded_len() {
+        assert_eq!(
+            Encoding::Base64Sortable.encode(&[0; FLAKE_ID_LEN]).len(),
+            20
+        );
+        assert_eq!(Encoding::Base64Sortable.encoded_len(FLAKE_ID_LEN), 20);
+    }
+
+    #[test]
+    fn test_sortable_decode_invalid_character() {
+        let mut bytes = [0; FLAKE_ID_LEN];
+        assert_eq!(
+            Encoding::Base64Sortable.decode("AAAAAAAA=AAAAAAAAAAA", &mut bytes),
+            Err(DecodeError::InvalidCharacter(8, b'='))
+        );
+    }
+
+    #[test]
+    fn test_crockford_and_hex_encoded_len() {
+        let bytes = [0xff; FLAKE_ID_LEN];
+        assert_eq!(
+            Encoding::Base32Crockford.encode(&bytes),
+            "ZZZZZZZZZZZZZZZZZZZZZZZZ"
+        );
+        assert_eq!(Encoding::Hex.encode(&bytes), "ff".repeat(FLAKE_ID_LEN));
+        assert_eq!(Encoding::Base32Crockford.encoded_len(FLAKE_ID_LEN), 24);
+        assert_eq!(Encoding::Hex.encoded_len(FLAKE_ID_LEN), 30);
+    }
+
+    #[test]
+    fn test_crockford_decode_is_forgiving() {
+        let mut expected = [0; FLAKE_ID_LEN];
+        Encoding::Base32Crockford
+            .decode("01ABCDEFGHJKMNPQRSTVWXYZ", &mut expected)
+            .unwrap();
+        let mut decoded = [0; FLAKE_ID_LEN];
+        Encoding::Base32Crockford
+            .decode("oiabcdefghjkmnpqrstvwxyz", &mut decoded)
+            .unwrap();
+        assert_eq!(decoded, expected);
+        Encoding::Base32Crockford
+            .decode("OLABCDEFGHJKMNPQRSTVWXYZ", &mut decoded)
+            .unwrap();
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn test_crockford_decode_invalid_character() {
+        let mut bytes = [0; FLAKE_ID_LEN];
+        assert_eq!(
+            Encoding::Base32Crockford.decode("0000000000U0000000000000", &mut bytes),
+            Err(DecodeError::InvalidCharacter(10, b'U'))
+        );
+    }
+
+    #[test]
+    fn test_hex_decode() {
+        let mut bytes = [0; FLAKE_ID_LEN];
+        Encoding::Hex
+            .decode("000102030405060708090A0b0C0d0E", &mut bytes)
+            .unwrap();
+        assert_eq!(bytes, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14]);
+        assert_eq!(
+            Encoding::Hex.decode("00010203040506070809", &mut bytes),
+            Err(DecodeError::InvalidLength(20))
+        );
+        assert_eq!(
+            Encoding::Hex.decode("00010203040506070809g0b0c0d0e0", &mut bytes),
+            Err(DecodeError::InvalidCharacter(20, b'g'))
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_sortable_string_order_is_byte_order(
+            a in any::<[u8; FLAKE_ID_LEN]>(),
+            mut b in any::<[u8; FLAKE_ID_LEN]>(),
+            shared_prefix in 0..=FLAKE_ID_LEN,
+        ) {
+            b[..shared_prefix].copy_from_slice(&a[..shared_prefix]);
+            let encoded_a = Encoding::Base64Sortable.encode(&a);
+            let encoded_b = Encoding::Base64Sortable.encode(&b);
+            prop_assert_eq!(a.cmp(&b), encoded_a.cmp(&encoded_b));
+        }
+
+        #[test]
+        fn prop_sortable_round_trip(a in any::<[u8; FLAKE_ID_LEN]>()) {
+            let mut decoded = [0; FLAKE_ID_LEN];
+            Encoding::Base64Sortable.decode(&Encoding::Base64Sortable.encode(&a), &mut decoded).unwrap();
+            prop_assert_eq!(a, decoded);
+        }
+
+        #[test]
+        fn prop_case_insensitive_string_order_is_byte_order(
+            a in any::<[u8; FLAKE_ID_LEN]>(),
+            mut b in any::<[u8; FLAKE_ID_LEN]>(),
+            shared_prefix in 0..=FLAKE_ID_LEN,
+        ) {
+            b[..shared_prefix].copy_from_slice(&a[..shared_prefix]);
+            for encoding in [Encoding::Base32Crockford, Encoding::Hex].iter() {
+                prop_assert_eq!(a.cmp(&b), encoding.encode(&a).cmp(&encoding.encode(&b)));
+            }
+        }
+
+        #[test]
+        fn prop_case_insensitive_round_trip(a in any::<[u8; FLAKE_ID_LEN]>()) {
+            for encoding in [Encoding::Base32Crockford, Encoding::Hex].iter() {
+                let mut decoded = [0; FLAKE_ID_LEN];
+                encoding.decode(&encoding.encode(&a).to_lowercase(), &mut decoded).unwrap();
+                prop_assert_eq!(a, decoded);
+            }
+        }
+
+        #[test]
+        fn prop_url_round_trip(a in any::<[u8; FLAKE_ID_LEN]>()) {
+            let mut decoded = [0; FLAKE_ID_LEN];
+            Encoding::Base64Url.decode(&Encoding::Base64Url.encode(&a), &mut decoded).unwrap();
+            prop_assert_eq!(a, decoded);
+        }
+    }
+}
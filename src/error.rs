@@ -0,0 +1,103 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    InvalidLength(usize),
+    InvalidCharacter(usize, u8),
+    OutOfRange,
+    InvalidUuid,
+    TimestampOutOfRange,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::InvalidLength(len) => write!(f, "Invalid flake id length {}", len),
+            DecodeError::InvalidCharacter(index, byte) => write!(
+                f,
+                "Invalid character {:?} at offset {}",
+                byte as char, index
+            ),
+            DecodeError::OutOfRange => write!(f, "Value does not fit in a flake id"),
+            DecodeError::InvalidUuid => write!(f, "Not a UUID produced from a flake id"),
+            DecodeError::TimestampOutOfRange => {
+                write!(f, "Timestamp is later than the system time can represent")
+            }
+        }
+    }
+}
+
+impl error::Error for DecodeError {}
+
+#[derive(Debug)]
+pub enum Error {
+    NoInterface,
+    InterfaceEnumeration(interfaces::InterfacesError),
+    NoHardwareAddress(String),
+    MissingEnvVar(String),
+    InvalidSeed(String),
+    Random(getrandom::Error),
+    Io(io::Error),
+    InvalidLayout(&'static str),
+    WorkerIdOutOfRange(u64),
+    ShardOutOfRange(u64),
+    ClockMovedBackwards(Duration),
+    SequenceExhausted,
+    BatchTooLarge(usize),
+    BufferTooSmall(usize),
+    InvalidEpoch,
+    TimestampOverflow,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::NoInterface => write!(f, "Can't find an suitable interface address"),
+            Error::InterfaceEnumeration(ref e) => write!(f, "Error retrieving interfaces: {}", e),
+            Error::NoHardwareAddress(ref name) => {
+                write!(f, "Interface {} has no hardware address", name)
+            }
+            Error::MissingEnvVar(ref name) => write!(f, "Environment variable {} is not set", name),
+            Error::InvalidSeed(ref value) => write!(f, "Invalid seed {:?}", value),
+            Error::Random(ref e) => write!(f, "Error generating random seed: {}", e),
+            Error::Io(ref e) => write!(f, "{}", e),
+            Error::InvalidLayout(reason) => write!(f, "Invalid layout: {}", reason),
+            Error::WorkerIdOutOfRange(worker_id) => {
+                write!(f, "Worker id {} does not fit the layout", worker_id)
+            }
+            Error::ShardOutOfRange(shard) => write!(f, "Shard {} does not fit the layout", shard),
+            Error::ClockMovedBackwards(skew) => {
+                write!(f, "Clock moved backwards by {:?}", skew)
+            }
+            Error::SequenceExhausted => write!(f, "Sequence exhausted for the current tick"),
+            Error::BufferTooSmall(needed) => {
+                write!(f, "Buffer is too small, {} bytes are needed", needed)
+            }
+            Error::BatchTooLarge(count) => {
+                write!(
+                    f,
+                    "Batch of {} ids exceeds the sequence range of one tick",
+                    count
+                )
+            }
+            Error::InvalidEpoch => write!(f, "Epoch is later than the current time"),
+            Error::TimestampOverflow => {
+                write!(f, "Time since the epoch no longer fits the timestamp field")
+            }
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::InterfaceEnumeration(ref e) => Some(e),
+            Error::Random(ref e) => Some(e),
+            Error::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,383 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::decoder::Decoder;
+use crate::encoding::Encoding;
+use crate::error::DecodeError;
+use crate::flake_str::FlakeStr;
+
+pub const FLAKE_ID_LEN: usize = 15;
+
+pub const MAX_FLAKE_ID_LEN: usize = 16;
+
+// The byte lengths of the 64, 96, 120 and 128-bit layouts.
+const VALID_LENS: [usize; 4] = [8, 12, FLAKE_ID_LEN, MAX_FLAKE_ID_LEN];
+
+const UUID_VERSION: u128 = 0x7;
+
+const UUID_VARIANT: u128 = 0b10;
+
+const UUID_SEED_LOW_MASK: u128 = (1 << 36) - 1;
+
+/// The raw bytes of a flake id, big-endian so that byte order matches
+/// generation order. Ids in the default layout are 15 bytes: a 48-bit
+/// timestamp, the 6-byte seed and a 24-bit sequence. Other layouts produce
+/// 8, 12 or 16 bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FlakeId {
+    bytes: [u8; MAX_FLAKE_ID_LEN],
+    len: u8,
+}
+
+impl FlakeId {
+    pub fn from_bytes(bytes: [u8; FLAKE_ID_LEN]) -> FlakeId {
+        let mut flake_id = FlakeId {
+            bytes: [0; MAX_FLAKE_ID_LEN],
+            len: FLAKE_ID_LEN as u8,
+        };
+        flake_id.bytes[..FLAKE_ID_LEN].copy_from_slice(&bytes);
+        flake_id
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<FlakeId, DecodeError> {
+        if !VALID_LENS.contains(&bytes.len()) {
+            return Err(DecodeError::InvalidLength(bytes.len()));
+        }
+        let mut flake_id = FlakeId {
+            bytes: [0; MAX_FLAKE_ID_LEN],
+            len: bytes.len() as u8,
+        };
+        flake_id.bytes[..bytes.len()].copy_from_slice(bytes);
+        Ok(flake_id)
+    }
+
+    pub(crate) fn from_u128_with_len(value: u128, len: usize) -> FlakeId {
+        let mut flake_id = FlakeId {
+            bytes: [0; MAX_FLAKE_ID_LEN],
+            len: len as u8,
+        };
+        flake_id.bytes[..len].copy_from_slice(&value.to_be_bytes()[MAX_FLAKE_ID_LEN - len..]);
+        flake_id
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+
+    pub fn byte_len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn encode(&self, encoding: Encoding) -> String {
+        encoding.encode(self.as_bytes())
+    }
+
+    /// Like `encode`, without allocating.
+    pub fn encode_str(&self, encoding: Encoding) -> FlakeStr {
+        FlakeStr::new(self, encoding)
+    }
+
+    /// Decodes an id of any supported length, inferred from the length of
+    /// `encoded`.
+    pub fn decode(encoded: &str, encoding: Encoding) -> Result<FlakeId, DecodeError> {
+        let len = VALID_LENS
+            .iter()
+            .cloned()
+            .find(|&len| encoding.encoded_len(len) == encoded.len())
+            .ok_or(DecodeError::InvalidLength(encoded.len()))?;
+        let mut flake_id = FlakeId {
+            bytes: [0; MAX_FLAKE_ID_LEN],
+            len: len as u8,
+        };
+        encoding.decode(encoded, &mut flake_id.bytes[..len])?;
+        Ok(flake_id)
+    }
+
+    /// The timestamp assuming the default layout and milliseconds since the
+    /// Unix epoch, which is how `SnowFlaker::new` generates ids. Use a
+    /// `Decoder` for anything else.
+    pub fn timestamp(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(crate::get_uint(&self.bytes, 0, 6))
+    }
+
+    /// The seed assuming the default layout.
+    pub fn seed(&self) -> [u8; 6] {
+        let mut seed = [0; 6];
+        seed.copy_from_slice(&self.bytes[6..12]);
+        seed
+    }
+
+    /// The sequence assuming the default layout.
+    pub fn sequence(&self) -> u32 {
+        crate::get_uint(&self.bytes, 12, 3) as u32
+    }
+
+    pub fn to_u128(&self) -> u128 {
+        self.as_bytes()
+            .iter()
+            .fold(0, |value, &byte| (value << 8) | u128::from(byte))
+    }
+
+    pub fn from_u128(value: u128) -> Result<FlakeId, DecodeError> {
+        if value >> (FLAKE_ID_LEN * 8) != 0 {
+            return Err(DecodeError::OutOfRange);
+        }
+        Ok(FlakeId::from_u128_with_len(value, FLAKE_ID_LEN))
+    }
+
+    /// Packs an id in the default layout into a version 7, RFC 4122 variant
+    /// UUID. The timestamp
+    /// fills the leading 48 bits; the seed and sequence are spread over the
+    /// 74 bits left around the version and variant fields.
+    pub fn to_uuid(&self) -> [u8; 16] {
+        let timestamp = u128::from(crate::get_uint(&self.bytes, 0, 6));
+        let seed = u128::from(crate::get_uint(&self.bytes, 6, 6));
+        let sequence = u128::from(self.sequence());
+        let uuid = (timestamp << 80)
+            | (UUID_VERSION << 76)
+            | ((seed >> 36) << 64)
+            | (UUID_VARIANT << 62)
+            | ((seed & UUID_SEED_LOW_MASK) << 26)
+            | (sequence << 2);
+        uuid.to_be_bytes()
+    }
+
+    pub fn from_uuid(uuid: &[u8; 16]) -> Result<FlakeId, DecodeError> {
+        let uuid = u128::from_be_bytes(*uuid);
+        if (uuid >> 76) & 0xf != UUID_VERSION
+            || (uuid >> 62) & 0x3 != UUID_VARIANT
+            || uuid & 0x3 != 0
+        {
+            return Err(DecodeError::InvalidUuid);
+        }
+        let timestamp = (uuid >> 80) as u64;
+        let seed = ((((uuid >> 64) & 0xfff) << 36) | ((uuid >> 26) & UUID_SEED_LOW_MASK)) as u64;
+        let sequence = ((uuid >> 2) & 0xff_ffff) as u64;
+        let mut bytes = [0; FLAKE_ID_LEN];
+        crate::put_uint(&mut bytes, timestamp, 0, 6);
+        crate::put_uint(&mut bytes, seed, 6, 6);
+        crate::put_uint(&mut bytes, sequence, 12, 3);
+        Ok(FlakeId::from_bytes(bytes))
+    }
+
+    /// The components assuming the id was generated like `SnowFlaker::new`
+    /// does. Use a `Decoder` for anything else.
+    pub fn components(&self) -> Components {
+        Decoder::default().components(self)
+    }
+}
+
+/// The parts a `Generator` combined to mint a flake id. Fields missing from
+/// the id's layout are zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Components {
+    pub timestamp: SystemTime,
+    pub seed: [u8; 6],
+    pub sequence: u32,
+    pub shard: u32,
+    pub random: u64,
+}
+
+impl Components {
+    /// Milliseconds from the Unix epoch to the timestamp, or zero if it is
+    /// earlier.
+    pub fn timestamp_ms(&self) -> u64 {
+        self.timestamp
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or(0)
+    }
+
+    /// The seed as colon-separated lowercase hex, like a MAC address.
+    pub fn seed_hex(&self) -> String {
+        let bytes: Vec<String> = self
+            .seed
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        bytes.join(":")
+    }
+}
+
+impl From<[u8; FLAKE_ID_LEN]> for FlakeId {
+    fn from(bytes: [u8; FLAKE_ID_LEN]) -> FlakeId {
+        FlakeId::from_bytes(bytes)
+    }
+}
+
+impl From<FlakeId> for u128 {
+    fn from(flake_id: FlakeId) -> u128 {
+        flake_id.to_u128()
+    }
+}
+
+impl AsRef<[u8]> for FlakeId {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl fmt::Display for FlakeId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.encode(Encoding::Base64Url))
+    }
+}
+
+impl FromStr for FlakeId {
+    type Err = DecodeError;
+
+    fn from_str(s: &str) -> Result<FlakeId, DecodeError> {
+        FlakeId::decode(s, Encoding::Base64Url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_display_matches_url_safe_base64() {
+        let id = FlakeId::from_bytes([0xfb; FLAKE_ID_LEN]);
+        assert_eq!(
+            id.to_string(),
+            base64::encode_config(&[0xfb; FLAKE_ID_LEN], base64::URL_SAFE)
+        );
+    }
+
+    #[test]
+    fn test_from_str_round_trip() {
+        let mut bytes = [0; FLAKE_ID_LEN];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = (i * 17) as u8;
+        }
+        let id = FlakeId::from_bytes(bytes);
+        assert_eq!(id.to_string().parse::<FlakeId>(), Ok(id));
+    }
+
+    #[test]
+    fn test_from_str_invalid_length() {
+        assert_eq!(
+            "AAAA".parse::<FlakeId>(),
+            Err(DecodeError::InvalidLength(4))
+        );
+    }
+
+    #[test]
+    fn test_from_str_invalid_character() {
+        assert_eq!(
+            "AAAAAAAAAAAAAAAAAAA+".parse::<FlakeId>(),
+            Err(DecodeError::InvalidCharacter(19, b'+'))
+        );
+    }
+
+    #[test]
+    fn test_decode_sortable() {
+        let id = FlakeId::from_bytes([0x5a; FLAKE_ID_LEN]);
+        let encoded = id.encode(Encoding::Base64Sortable);
+        assert_eq!(FlakeId::decode(&encoded, Encoding::Base64Sortable), Ok(id));
+    }
+
+    #[test]
+    fn test_from_slice_lengths() {
+        for &len in VALID_LENS.iter() {
+            let bytes = vec![0xa5; len];
+            let flake_id = FlakeId::from_slice(&bytes).unwrap();
+            assert_eq!(flake_id.as_bytes(), &bytes[..]);
+            assert_eq!(
+                FlakeId::decode(
+                    &flake_id.encode(Encoding::Base32Crockford),
+                    Encoding::Base32Crockford
+                ),
+                Ok(flake_id)
+            );
+        }
+        assert_eq!(
+            FlakeId::from_slice(&[0; 10]),
+            Err(DecodeError::InvalidLength(10))
+        );
+    }
+
+    #[test]
+    fn test_components() {
+        let bytes = [
+            0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 1, 2, 3, 4, 5, 6, 0x01, 0x00, 0x02,
+        ];
+        let components = FlakeId::from_bytes(bytes).components();
+        assert_eq!(
+            components,
+            Components {
+                timestamp: UNIX_EPOCH + Duration::from_millis(256),
+                seed: [1, 2, 3, 4, 5, 6],
+                sequence: 65538,
+                shard: 0,
+                random: 0,
+            }
+        );
+        assert_eq!(components.timestamp_ms(), 256);
+        assert_eq!(components.seed_hex(), "01:02:03:04:05:06");
+    }
+
+    #[test]
+    fn test_u128_round_trip() {
+        let bytes = [
+            0x01, 0x6d, 0x3c, 0x5a, 0x2e, 0x10, 1, 2, 3, 4, 5, 6, 0xab, 0xcd, 0xef,
+        ];
+        let id = FlakeId::from_bytes(bytes);
+        assert_eq!(id.to_u128(), 0x01_6d3c_5a2e_1001_0203_0405_06ab_cdef);
+        assert_eq!(FlakeId::from_u128(id.to_u128()), Ok(id));
+        assert_eq!(FlakeId::from_u128(1 << 120), Err(DecodeError::OutOfRange));
+    }
+
+    #[test]
+    fn test_u128_order_matches_id_order() {
+        let lower = FlakeId::from_bytes([0x7f; FLAKE_ID_LEN]);
+        let higher = FlakeId::from_bytes([0x80; FLAKE_ID_LEN]);
+        assert!(lower.to_u128() < higher.to_u128());
+    }
+
+    #[test]
+    fn test_uuid_round_trip() {
+        let bytes = [
+            0x01, 0x6d, 0x3c, 0x5a, 0x2e, 0x10, 0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54, 0xff, 0xff,
+            0xff,
+        ];
+        let id = FlakeId::from_bytes(bytes);
+        let uuid = id.to_uuid();
+        assert_eq!(uuid[..6], bytes[..6]);
+        assert_eq!(uuid[6] >> 4, 0x7);
+        assert_eq!(uuid[8] >> 6, 0b10);
+        assert_eq!(FlakeId::from_uuid(&uuid), Ok(id));
+        assert_eq!(
+            FlakeId::from_uuid(&uuid).unwrap().components(),
+            id.components()
+        );
+    }
+
+    #[test]
+    fn test_uuid_preserves_order() {
+        let mut lower = [0; FLAKE_ID_LEN];
+        let mut higher = [0; FLAKE_ID_LEN];
+        lower[11] = 0xff;
+        higher[14] = 0x01;
+        higher[10] = 0x01;
+        assert!(FlakeId::from_bytes(lower).to_uuid() < FlakeId::from_bytes(higher).to_uuid());
+    }
+
+    #[test]
+    fn test_from_uuid_rejects_other_versions() {
+        let mut uuid = FlakeId::from_bytes([0x42; FLAKE_ID_LEN]).to_uuid();
+        uuid[6] = (uuid[6] & 0x0f) | 0x40;
+        assert_eq!(FlakeId::from_uuid(&uuid), Err(DecodeError::InvalidUuid));
+    }
+
+    #[test]
+    fn test_ordering_follows_bytes() {
+        let mut lower = [0; FLAKE_ID_LEN];
+        let mut higher = [0; FLAKE_ID_LEN];
+        lower[5] = 1;
+        higher[4] = 1;
+        assert!(FlakeId::from_bytes(lower) < FlakeId::from_bytes(higher));
+    }
+}
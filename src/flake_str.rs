@@ -0,0 +1,147 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::str;
+
+use crate::encoding::Encoding;
+use crate::flake_id::{FlakeId, MAX_FLAKE_ID_LEN};
+
+/// The longest encoding of any flake id: 16 bytes in hex.
+pub const MAX_ENCODED_LEN: usize = MAX_FLAKE_ID_LEN * 2;
+
+/// An encoded flake id held on the stack, so formatting one never allocates.
+/// It compares and hashes like its `str`, as `Borrow<str>` requires.
+#[derive(Clone, Copy)]
+pub struct FlakeStr {
+    buf: [u8; MAX_ENCODED_LEN],
+    len: u8,
+}
+
+impl FlakeStr {
+    pub fn new(flake_id: &FlakeId, encoding: Encoding) -> FlakeStr {
+        let mut buf = [0; MAX_ENCODED_LEN];
+        let len = encoding.encode_into(flake_id.as_bytes(), &mut buf);
+        FlakeStr {
+            buf,
+            len: len as u8,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        str::from_utf8(&self.buf[..self.len as usize]).expect("alphabets are ASCII")
+    }
+}
+
+impl Deref for FlakeStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for FlakeStr {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Borrow<str> for FlakeStr {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq for FlakeStr {
+    fn eq(&self, other: &FlakeStr) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for FlakeStr {}
+
+impl PartialOrd for FlakeStr {
+    fn partial_cmp(&self, other: &FlakeStr) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FlakeStr {
+    fn cmp(&self, other: &FlakeStr) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Hash for FlakeStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl PartialEq<str> for FlakeStr {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<'a> PartialEq<&'a str> for FlakeStr {
+    fn eq(&self, other: &&'a str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Debug for FlakeStr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for FlakeStr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_matches_allocating_encode() {
+        let flake_id = FlakeId::from_slice(&[0xfe; MAX_FLAKE_ID_LEN]).unwrap();
+        for &encoding in [
+            Encoding::Base64Url,
+            Encoding::Base64Sortable,
+            Encoding::Base32Crockford,
+            Encoding::Hex,
+        ]
+        .iter()
+        {
+            let flake_str = FlakeStr::new(&flake_id, encoding);
+            assert_eq!(flake_str, flake_id.encode(encoding).as_str());
+            assert_eq!(flake_str.len(), encoding.encoded_len(MAX_FLAKE_ID_LEN));
+        }
+    }
+
+    #[test]
+    fn test_hash_map_lookup_by_str() {
+        let flake_id = FlakeId::from_bytes([7; 15]);
+        let mut map = HashMap::new();
+        map.insert(FlakeStr::new(&flake_id, Encoding::Hex), 1);
+        assert_eq!(map.get(flake_id.encode(Encoding::Hex).as_str()), Some(&1));
+        let shorter = FlakeStr::new(&FlakeId::from_slice(&[7; 8]).unwrap(), Encoding::Hex);
+        assert!(shorter < FlakeStr::new(&flake_id, Encoding::Hex));
+    }
+
+    #[test]
+    fn test_display_and_debug() {
+        let flake_id = FlakeId::from_bytes([0; 15]);
+        let flake_str = FlakeStr::new(&flake_id, Encoding::Base64Url);
+        assert_eq!(flake_str.to_string(), flake_id.to_string());
+        assert_eq!(format!("{:?}", flake_str), "\"AAAAAAAAAAAAAAAAAAAA\"");
+    }
+}
@@ -0,0 +1,145 @@
+//! A gRPC id service, behind the `grpc` feature. The protocol is in
+//! `proto/rustflake.proto`.
+
+use std::pin::Pin;
+use std::sync::Arc;
+
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::{Request, Response, Status};
+
+use crate::error::Error;
+use crate::flake_id::FlakeId;
+use crate::{Generator, MAX_REQUEST_IDS};
+
+#[allow(clippy::all)]
+pub mod proto {
+    tonic::include_proto!("rustflake");
+}
+
+use self::proto::id_service_server::{IdService, IdServiceServer};
+use self::proto::{
+    Components, DecodeRequest, Id, Ids, NextIdRequest, NextIdsRequest, StreamIdsRequest,
+};
+
+// Ids are minted this many at a time for `StreamIds`.
+const STREAM_BATCH: u64 = 1_000;
+
+/// Serves `IdService` from a shared `Generator`.
+#[derive(Debug, Clone)]
+pub struct IdServer {
+    generator: Arc<Generator>,
+}
+
+impl IdServer {
+    pub fn new(generator: Arc<Generator>) -> IdServer {
+        IdServer { generator }
+    }
+
+    pub fn into_service(self) -> IdServiceServer<IdServer> {
+        IdServiceServer::new(self)
+    }
+
+    fn id(&self, flake_id: FlakeId) -> Id {
+        Id {
+            id: flake_id.encode(self.generator.encoding()),
+            raw: flake_id.as_bytes().to_vec(),
+        }
+    }
+}
+
+/// Serves `IdService` on `listener` until the listener fails.
+pub async fn serve(
+    generator: Arc<Generator>,
+    listener: TcpListener,
+) -> Result<(), tonic::transport::Error> {
+    tonic::transport::Server::builder()
+        .add_service(IdServer::new(generator).into_service())
+        .serve_with_incoming(TcpListenerStream::new(listener))
+        .await
+}
+
+fn unavailable(e: Error) -> Status {
+    Status::unavailable(e.to_string())
+}
+
+#[tonic::async_trait]
+impl IdService for IdServer {
+    async fn next_id(&self, _request: Request<NextIdRequest>) -> Result<Response<Id>, Status> {
+        let flake_id = self.generator.try_generate_id().map_err(unavailable)?;
+        Ok(Response::new(self.id(flake_id)))
+    }
+
+    async fn next_ids(&self, request: Request<NextIdsRequest>) -> Result<Response<Ids>, Status> {
+        let count = request.into_inner().count;
+        if count == 0 || count as usize > MAX_REQUEST_IDS {
+            return Err(Status::invalid_argument(format!(
+                "count must be between 1 and {}",
+                MAX_REQUEST_IDS
+            )));
+        }
+        let batch = self
+            .generator
+            .reserve(count as usize)
+            .map_err(unavailable)?;
+        Ok(Response::new(Ids {
+            ids: batch.map(|flake_id| self.id(flake_id)).collect(),
+        }))
+    }
+
+    type StreamIdsStream = Pin<Box<ReceiverStream<Result<Id, Status>>>>;
+
+    async fn stream_ids(
+        &self,
+        request: Request<StreamIdsRequest>,
+    ) -> Result<Response<Self::StreamIdsStream>, Status> {
+        let mut remaining = request.into_inner().count;
+        if remaining == 0 {
+            return Err(Status::invalid_argument("count must be at least 1"));
+        }
+        let (sender, receiver) = mpsc::channel(STREAM_BATCH as usize);
+        let server = self.clone();
+        tokio::spawn(async move {
+            while remaining > 0 {
+                let count = remaining.min(STREAM_BATCH);
+                let ids: Result<Vec<Id>, Status> = server
+                    .generator
+                    .reserve(count as usize)
+                    .map(|batch| batch.map(|flake_id| server.id(flake_id)).collect())
+                    .map_err(unavailable);
+                match ids {
+                    Ok(ids) => {
+                        for id in ids {
+                            if sender.send(Ok(id)).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    Err(status) => {
+                        let _ = sender.send(Err(status)).await;
+                        return;
+                    }
+                }
+                remaining -= count;
+            }
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(receiver))))
+    }
+
+    async fn decode(
+        &self,
+        request: Request<DecodeRequest>,
+    ) -> Result<Response<Components>, Status> {
+        let components = self
+            .generator
+            .decoder()
+            .decode(&request.into_inner().id)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        Ok(Response::new(Components {
+            timestamp_ms: components.timestamp_ms(),
+            seed: components.seed.to_vec(),
+            sequence: components.sequence,
+        }))
+    }
+}
@@ -0,0 +1,297 @@
+use crate::error::Error;
+use crate::flake_id::FlakeId;
+
+const MAX_FIELDS: usize = 6;
+
+/// A field of a flake id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Field {
+    /// Ticks since the generator's epoch, at most 64 bits.
+    Timestamp,
+    /// The generator's seed, folded down when narrower than 48 bits.
+    Node,
+    /// The generator's sequence, at most 32 bits.
+    Sequence,
+    /// Fresh random bits for every id, at most 64 bits.
+    Random,
+    /// The shard a generator was built for, at most 32 bits.
+    Shard,
+    /// Always zero, e.g. to keep the sign bit of a 64-bit id clear.
+    Reserved,
+}
+
+impl Field {
+    fn max_width(self) -> u8 {
+        match self {
+            Field::Timestamp | Field::Random | Field::Reserved => 64,
+            Field::Node => 48,
+            Field::Sequence | Field::Shard => 32,
+        }
+    }
+}
+
+/// The widths and order of the fields in a flake id, most significant first.
+/// The default is the original 120-bit layout: a 48-bit timestamp, the
+/// 48-bit seed and a 24-bit sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Layout {
+    fields: [(Field, u8); MAX_FIELDS],
+    len: usize,
+}
+
+impl Layout {
+    pub fn builder() -> LayoutBuilder {
+        LayoutBuilder { fields: Vec::new() }
+    }
+
+    pub fn fields(&self) -> &[(Field, u8)] {
+        &self.fields[..self.len]
+    }
+
+    pub fn width_bits(&self) -> u32 {
+        self.fields()
+            .iter()
+            .map(|&(_, width)| u32::from(width))
+            .sum()
+    }
+
+    pub fn byte_len(&self) -> usize {
+        self.width_bits() as usize / 8
+    }
+
+    /// The width of `field`, or zero if the layout doesn't have it.
+    pub fn width(&self, field: Field) -> u8 {
+        self.fields()
+            .iter()
+            .find(|&&(f, _)| f == field)
+            .map_or(0, |&(_, width)| width)
+    }
+
+    pub fn has(&self, field: Field) -> bool {
+        self.width(field) != 0
+    }
+
+    pub fn max_value(&self, field: Field) -> u64 {
+        mask(self.width(field))
+    }
+
+    /// Builds an id from the value of each field, truncating values to their
+    /// field's width.
+    pub fn compose<F: FnMut(Field) -> u64>(&self, mut value: F) -> FlakeId {
+        let mut id: u128 = 0;
+        for &(field, width) in self.fields() {
+            let field_value = match field {
+                Field::Reserved => 0,
+                _ => value(field) & mask(width),
+            };
+            id = (id << width) | u128::from(field_value);
+        }
+        FlakeId::from_u128_with_len(id, self.byte_len())
+    }
+
+    /// Reads the value of `field` from `flake_id`, or zero if the layout
+    /// doesn't have it.
+    pub fn value(&self, flake_id: &FlakeId, field: Field) -> u64 {
+        let mut shift = self.width_bits();
+        for &(f, width) in self.fields() {
+            shift -= u32::from(width);
+            if f == field {
+                return (flake_id.to_u128() >> shift) as u64 & mask(width);
+            }
+        }
+        0
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Layout {
+        let mut fields = [(Field::Reserved, 0); MAX_FIELDS];
+        fields[0] = (Field::Timestamp, 48);
+        fields[1] = (Field::Node, 48);
+        fields[2] = (Field::Sequence, 24);
+        Layout { fields, len: 3 }
+    }
+}
+
+/// Lists the fields of a `Layout` from the most significant down.
+#[derive(Debug, Clone)]
+pub struct LayoutBuilder {
+    fields: Vec<(Field, u8)>,
+}
+
+impl LayoutBuilder {
+    pub fn field(mut self, field: Field, width: u8) -> LayoutBuilder {
+        self.fields.push((field, width));
+        self
+    }
+
+    pub fn timestamp(self, width: u8) -> LayoutBuilder {
+        self.field(Field::Timestamp, width)
+    }
+
+    pub fn node(self, width: u8) -> LayoutBuilder {
+        self.field(Field::Node, width)
+    }
+
+    pub fn sequence(self, width: u8) -> LayoutBuilder {
+        self.field(Field::Sequence, width)
+    }
+
+    pub fn random(self, width: u8) -> LayoutBuilder {
+        self.field(Field::Random, width)
+    }
+
+    pub fn shard(self, width: u8) -> LayoutBuilder {
+        self.field(Field::Shard, width)
+    }
+
+    pub fn reserved(self, width: u8) -> LayoutBuilder {
+        self.field(Field::Reserved, width)
+    }
+
+    pub fn build(self) -> Result<Layout, Error> {
+        if self.fields.len() > MAX_FIELDS {
+            return Err(Error::InvalidLayout("too many fields"));
+        }
+        let mut layout = Layout {
+            fields: [(Field::Reserved, 0); MAX_FIELDS],
+            len: self.fields.len(),
+        };
+        for (i, &(field, width)) in self.fields.iter().enumerate() {
+            if width == 0 || width > field.max_width() {
+                return Err(Error::InvalidLayout("field width out of range"));
+            }
+            if field != Field::Reserved && layout.has(field) {
+                return Err(Error::InvalidLayout("field appears more than once"));
+            }
+            layout.fields[i] = (field, width);
+        }
+        if !layout.has(Field::Timestamp) || !layout.has(Field::Sequence) {
+            return Err(Error::InvalidLayout(
+                "timestamp and sequence fields are required",
+            ));
+        }
+        match layout.width_bits() {
+            64 | 96 | 120 | 128 => Ok(layout),
+            _ => Err(Error::InvalidLayout(
+                "total width must be 64, 96, 120 or 128 bits",
+            )),
+        }
+    }
+}
+
+fn mask(width: u8) -> u64 {
+    if width >= 64 {
+        u64::MAX
+    } else {
+        (1 << width) - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_default_layout() {
+        let layout = Layout::builder()
+            .timestamp(48)
+            .node(48)
+            .sequence(24)
+            .build()
+            .unwrap();
+        assert_eq!(layout, Layout::default());
+        assert_eq!(layout.width_bits(), 120);
+        assert_eq!(layout.byte_len(), 15);
+        assert_eq!(layout.max_value(Field::Sequence), (1 << 24) - 1);
+        assert!(!layout.has(Field::Shard));
+    }
+
+    #[test]
+    fn test_rejects_invalid_layouts() {
+        let invalid = [
+            Layout::builder()
+                .timestamp(48)
+                .node(48)
+                .sequence(23)
+                .build(),
+            Layout::builder().timestamp(48).node(48).build(),
+            Layout::builder().node(48).node(48).sequence(24).build(),
+            Layout::builder()
+                .timestamp(48)
+                .node(49)
+                .sequence(23)
+                .build(),
+            Layout::builder()
+                .timestamp(41)
+                .sequence(33)
+                .random(54)
+                .build(),
+            Layout::builder()
+                .timestamp(48)
+                .node(0)
+                .sequence(24)
+                .random(48)
+                .build(),
+        ];
+        for layout in invalid.iter() {
+            assert!(matches!(layout, Err(Error::InvalidLayout(_))));
+        }
+    }
+
+    #[test]
+    fn test_compose_and_read_back() {
+        let layout = Layout::builder()
+            .reserved(1)
+            .timestamp(41)
+            .shard(5)
+            .node(5)
+            .sequence(12)
+            .build()
+            .unwrap();
+        let flake_id = layout.compose(|field| match field {
+            Field::Timestamp => 1_234_567,
+            Field::Shard => 17,
+            Field::Node => 0xff,
+            Field::Sequence => 42,
+            _ => unreachable!(),
+        });
+        assert_eq!(flake_id.as_bytes().len(), 8);
+        assert_eq!(
+            flake_id.to_u128(),
+            (1_234_567 << 22) | (17 << 17) | (31 << 12) | 42
+        );
+        assert_eq!(layout.value(&flake_id, Field::Timestamp), 1_234_567);
+        assert_eq!(layout.value(&flake_id, Field::Shard), 17);
+        assert_eq!(layout.value(&flake_id, Field::Node), 31);
+        assert_eq!(layout.value(&flake_id, Field::Sequence), 42);
+        assert_eq!(layout.value(&flake_id, Field::Random), 0);
+    }
+
+    #[test]
+    fn test_128_bit_layout() {
+        let layout = Layout::builder()
+            .timestamp(64)
+            .node(32)
+            .random(32)
+            .sequence(0)
+            .build();
+        assert!(layout.is_err());
+        let layout = Layout::builder()
+            .timestamp(64)
+            .sequence(32)
+            .random(32)
+            .build()
+            .unwrap();
+        let flake_id = layout.compose(|field| match field {
+            Field::Timestamp => u64::MAX,
+            Field::Sequence => 1,
+            _ => 2,
+        });
+        assert_eq!(flake_id.as_bytes().len(), 16);
+        assert_eq!(layout.value(&flake_id, Field::Timestamp), u64::MAX);
+        assert_eq!(layout.value(&flake_id, Field::Sequence), 1);
+        assert_eq!(layout.value(&flake_id, Field::Random), 2);
+    }
+}
@@ -1,26 +1,325 @@
-#![feature(atomic_min_max)]
-#![feature(integer_atomics)]
-#![feature(test)]
-
 extern crate base64;
+extern crate getrandom;
+extern crate hostname;
 extern crate interfaces;
-extern crate test;
+extern crate portable_atomic;
+#[cfg(feature = "serde")]
+extern crate serde;
+
+mod batch;
+mod builder;
+mod checkpoint;
+mod clock;
+mod compact;
+mod decoder;
+#[cfg(feature = "serde")]
+pub mod encoded;
+mod encoding;
+mod error;
+mod flake_id;
+mod flake_str;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+mod layout;
+#[cfg(feature = "resp")]
+pub mod resp;
+mod seed;
+mod sequence;
+#[cfg(any(feature = "resp", feature = "thrift"))]
+mod server;
+mod sharded;
+mod state;
+#[cfg(feature = "thrift")]
+pub mod thrift;
+
+pub use batch::Batch;
+pub use builder::GeneratorBuilder;
+pub use checkpoint::{Checkpoint, FileCheckpoint};
+pub use clock::{Clock, ClockRollback, ManualClock, SystemClock, TimeUnit};
+pub use compact::{CompactComponents, CompactGenerator, CompactLayout, TWITTER_EPOCH_MS};
+pub use decoder::Decoder;
+pub use encoding::Encoding;
+pub use error::{DecodeError, Error};
+pub use flake_id::{Components, FlakeId, FLAKE_ID_LEN, MAX_FLAKE_ID_LEN};
+pub use flake_str::{FlakeStr, MAX_ENCODED_LEN};
+pub use layout::{Field, Layout, LayoutBuilder};
+pub use seed::{
+    parse_seed, resolve_seed, EnvSeed, ExplicitSeed, FileSeed, HostnameSeed, MacAddressSeed,
+    RandomSeed, SeedProvider,
+};
+pub use sequence::{OnExhausted, SequenceMode};
+pub use sharded::ShardedGenerator;
+
+use checkpoint::Checkpointer;
+use state::{Stamp, State};
 
+use std::cell::Cell;
 use std::cmp;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The most ids the servers hand out for one request.
+pub const MAX_REQUEST_IDS: usize = 10_000;
 
 #[derive(Debug)]
 pub struct Generator {
     seed: [u8; 6],
-    sequence: AtomicU64,
-    timestamp: AtomicU64,
+    state: State,
+    floor: u64,
+    epoch: SystemTime,
+    time_unit: TimeUnit,
+    encoding: Encoding,
+    clock: Arc<dyn Clock>,
+    rollback: ClockRollback,
+    clock_skew: AtomicU64,
+    sequence_mode: SequenceMode,
+    layout: Layout,
+    shard: u64,
+    checkpoint: Option<Arc<Checkpointer>>,
 }
 
 impl PartialEq for Generator {
     fn eq(&self, other: &Generator) -> bool {
         self.seed == other.seed
-            && self.sequence.load(Ordering::SeqCst) == other.sequence.load(Ordering::SeqCst)
+            && self.epoch == other.epoch
+            && self.time_unit == other.time_unit
+            && self.layout == other.layout
+            && self.shard == other.shard
+            && self.state == other.state
+            && self.floor == other.floor
+    }
+}
+
+impl Generator {
+    pub fn builder() -> GeneratorBuilder {
+        GeneratorBuilder::new()
+    }
+
+    pub fn try_new() -> Result<Generator, Error> {
+        Generator::with_provider(&MacAddressSeed)
+    }
+
+    pub fn with_provider<P: SeedProvider>(provider: &P) -> Result<Generator, Error> {
+        provider.seed().map(Generator::with_seed)
+    }
+
+    pub fn generate_id(&self) -> FlakeId {
+        match self.try_generate_id() {
+            Ok(flake_id) => flake_id,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    pub fn try_generate(&self) -> Result<String, Error> {
+        self.try_generate_id()
+            .map(|flake_id| flake_id.encode(self.encoding))
+    }
+
+    pub fn try_generate_id(&self) -> Result<FlakeId, Error> {
+        let now = self.current_ticks(self.state.load().0)?;
+        let (timestamp, sequence) = self
+            .state
+            .update(|last, sequence| self.advance(last, sequence, 1, now))?;
+        self.cover(timestamp)?;
+        Ok(self.compose(timestamp, sequence))
+    }
+
+    pub fn decoder(&self) -> Decoder {
+        Decoder::new(self.epoch, self.time_unit, self.encoding).with_layout(self.layout)
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub fn with_encoding(mut self, encoding: Encoding) -> Generator {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Generator {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    pub fn with_clock_rollback(mut self, rollback: ClockRollback) -> Generator {
+        self.rollback = rollback;
+        self
+    }
+
+    pub fn with_sequence_mode(mut self, sequence_mode: SequenceMode) -> Generator {
+        self.sequence_mode = sequence_mode;
+        self
+    }
+
+    /// The largest step backwards the clock has been seen to take.
+    pub fn clock_skew(&self) -> Duration {
+        self.time_unit
+            .duration(self.clock_skew.load(Ordering::Relaxed))
+    }
+
+    /// Claims `count` consecutive sequence numbers in a single atomic step
+    /// and returns an iterator over the ids they make, in generation order.
+    /// Fails with `Error::BatchTooLarge` if `count` exceeds the number of
+    /// sequence numbers in one tick. A batch that would run past the end of
+    /// the sequence starts again from zero in the next tick instead.
+    pub fn reserve(&self, count: usize) -> Result<Batch<'_>, Error> {
+        if count as u64 > self.layout.max_value(Field::Sequence) + 1 {
+            return Err(Error::BatchTooLarge(count));
+        }
+        if count == 0 {
+            return Ok(Batch::new(self, 0, 0, 0));
+        }
+        let now = self.current_ticks(self.state.load().0)?;
+        let (timestamp, sequence) = self
+            .state
+            .update(|last, sequence| self.advance(last, sequence, count as u64, now))?;
+        self.cover(timestamp)?;
+        Ok(Batch::new(self, timestamp, sequence, count as u64))
+    }
+
+    /// Like `reserve`, encoding each id with the generator's encoding.
+    pub fn generate_batch(&self, count: usize) -> Result<impl Iterator<Item = String> + '_, Error> {
+        let encoding = self.encoding;
+        self.reserve(count)
+            .map(move |batch| batch.map(move |flake_id| flake_id.encode(encoding)))
+    }
+
+    /// Like `SnowFlaker::generate`, without allocating.
+    pub fn generate_str(&self) -> FlakeStr {
+        self.generate_id().encode_str(self.encoding)
+    }
+
+    pub fn try_generate_str(&self) -> Result<FlakeStr, Error> {
+        self.try_generate_id()
+            .map(|flake_id| flake_id.encode_str(self.encoding))
+    }
+
+    /// Generates an id and encodes it into the start of `out`, failing with
+    /// `Error::BufferTooSmall` before claiming an id if it wouldn't fit.
+    pub fn generate_into<'b>(&self, out: &'b mut [u8]) -> Result<&'b str, Error> {
+        let len = self.encoding.encoded_len(self.layout.byte_len());
+        if out.len() < len {
+            return Err(Error::BufferTooSmall(len));
+        }
+        let flake_id = self.try_generate_id()?;
+        self.encoding.encode_into(flake_id.as_bytes(), out);
+        Ok(std::str::from_utf8(&out[..len]).expect("alphabets are ASCII"))
+    }
+
+    pub(crate) fn compose(&self, timestamp: u64, sequence: u64) -> FlakeId {
+        self.layout.compose(|field| match field {
+            Field::Timestamp => timestamp,
+            Field::Node => fold_seed(self.seed, self.layout.width(Field::Node)),
+            Field::Sequence => sequence,
+            Field::Random => random_u64(),
+            Field::Shard => self.shard,
+            Field::Reserved => 0,
+        })
+    }
+
+    fn cover(&self, timestamp: u64) -> Result<(), Error> {
+        match self.checkpoint {
+            Some(ref checkpoint) => checkpoint.cover(timestamp),
+            None => Ok(()),
+        }
+    }
+
+    // Picks the timestamp and first sequence for the next `count` ids given
+    // the latest ones and a clock reading that has already been through the
+    // rollback policy, returning the state to store alongside them. This is
+    // retried whenever another thread updates the state first, so it leaves
+    // the clock alone unless it has to wait for the next tick.
+    fn advance(
+        &self,
+        last: u64,
+        sequence: u64,
+        count: u64,
+        now: u64,
+    ) -> Result<(Stamp, Stamp), Error> {
+        let range = self.layout.max_value(Field::Sequence) + 1;
+        match self.sequence_mode {
+            SequenceMode::Continuous if sequence + count <= range => {
+                let timestamp = cmp::max(last, now);
+                Ok(((timestamp, sequence + count), (timestamp, sequence)))
+            }
+            // The sequence would wrap; starting it over within the same tick
+            // would repeat ids, and wrapping within a batch would break its
+            // order.
+            SequenceMode::Continuous => {
+                let now = if now > last {
+                    now
+                } else {
+                    self.next_tick(last)?
+                };
+                Ok(((now, count), (now, 0)))
+            }
+            SequenceMode::ResetPerTick(_) if now > last => Ok(((now, count), (now, 0))),
+            SequenceMode::ResetPerTick(_) if sequence + count <= range => {
+                Ok(((last, sequence + count), (last, sequence)))
+            }
+            SequenceMode::ResetPerTick(OnExhausted::Fail) => Err(Error::SequenceExhausted),
+            SequenceMode::ResetPerTick(OnExhausted::WaitForNextTick) => {
+                let now = self.next_tick(last)?;
+                Ok(((now, count), (now, 0)))
+            }
+        }
+    }
+
+    fn next_tick(&self, last: u64) -> Result<u64, Error> {
+        loop {
+            let now = self.clock_ticks()?;
+            if now > last {
+                return Ok(now);
+            }
+            thread::yield_now();
+        }
+    }
+
+    // Reads the clock once per id or batch, raised to the checkpoint floor,
+    // and applies the rollback policy if it is behind `last`. Being behind
+    // only the floor is not a rollback.
+    fn current_ticks(&self, last: u64) -> Result<u64, Error> {
+        loop {
+            let reading = self.clock_ticks()?;
+            let now = cmp::max(reading, self.floor);
+            if now >= last {
+                return Ok(now);
+            }
+            let skew = last - reading;
+            self.clock_skew.fetch_max(skew, Ordering::Relaxed);
+            let skew = self.time_unit.duration(skew);
+            match self.rollback {
+                ClockRollback::KeepLast => return Ok(now),
+                ClockRollback::Block => thread::sleep(skew),
+                ClockRollback::Fail(threshold) if skew > threshold => {
+                    return Err(Error::ClockMovedBackwards(skew))
+                }
+                ClockRollback::Fail(_) => return Ok(now),
+                ClockRollback::Notify(ref notify) => {
+                    notify(skew);
+                    return Ok(now);
+                }
+            }
+        }
+    }
+
+    // A clock reading before the epoch counts as the epoch itself, which the
+    // rollback policy then treats like any other step backwards.
+    fn clock_ticks(&self) -> Result<u64, Error> {
+        let now = self.time_unit.ticks_since(self.epoch, self.clock.now());
+        if now > self.layout.max_value(Field::Timestamp) {
+            return Err(Error::TimestampOverflow);
+        }
+        Ok(now)
     }
 }
 
@@ -37,61 +336,104 @@ impl SnowFlaker for Generator {
 
     fn with_seed(seed: [u8; 6]) -> Generator {
         Generator {
-            seed: seed,
-            sequence: AtomicU64::new(0),
-            timestamp: AtomicU64::new(0),
+            seed,
+            state: State::new(0, 0),
+            floor: 0,
+            epoch: UNIX_EPOCH,
+            time_unit: TimeUnit::default(),
+            encoding: Encoding::default(),
+            clock: Arc::new(SystemClock),
+            rollback: ClockRollback::default(),
+            clock_skew: AtomicU64::new(0),
+            sequence_mode: SequenceMode::default(),
+            layout: Layout::default(),
+            shard: 0,
+            checkpoint: None,
         }
     }
 
     fn generate(&self) -> String {
-        let now = SystemTime::now();
-        let since_epoch = now.duration_since(UNIX_EPOCH).expect("Time went backwards");
-        let since_epoch_in_ms = since_epoch.as_millis() as u64;
-        let previous_value = self
-            .timestamp
-            .fetch_max(since_epoch_in_ms, Ordering::Relaxed);
-        let max = cmp::max(previous_value, since_epoch_in_ms);
-        let mut flake_id = [0; 15];
-        put_uint(&mut flake_id, max, 0, 6);
-
-        copy_seed(&mut flake_id, self.seed);
-
-        let sequence = self.sequence.fetch_add(1, Ordering::SeqCst);
-        put_uint(&mut flake_id, sequence, 12, 3);
-
-        base64::encode_config(&flake_id, base64::URL_SAFE)
+        self.generate_id().encode(self.encoding)
     }
 }
 
 fn put_uint(byte_array: &mut [u8], long_value: u64, pos: u8, number_of_bytes: u8) {
     for i in 0..number_of_bytes {
-        let val = (long_value >> i * 8) as u8;
+        let val = (long_value >> (i * 8)) as u8;
         let index = (pos + number_of_bytes - i - 1) as usize;
         byte_array[index] = val;
     }
 }
 
-fn copy_seed(byte_array: &mut [u8], seed_array: [u8; 6]) {
-    for i in 0..seed_array.len() {
-        byte_array[i + 6] = seed_array[i];
+fn get_uint(byte_array: &[u8], pos: u8, number_of_bytes: u8) -> u64 {
+    let mut long_value = 0;
+    for i in 0..number_of_bytes {
+        long_value = (long_value << 8) | u64::from(byte_array[(pos + i) as usize]);
+    }
+    long_value
+}
+
+// XORs the seed down to `bits` bits so narrow node fields still depend on
+// every byte of it.
+fn fold_seed(seed: [u8; 6], bits: u8) -> u64 {
+    if bits == 0 {
+        return 0;
+    }
+    let mask = (1u64 << bits) - 1;
+    let mut remaining = get_uint(&seed, 0, 6);
+    let mut folded = 0;
+    while remaining != 0 {
+        folded ^= remaining & mask;
+        remaining >>= bits;
+    }
+    folded
+}
+
+// A per-thread xorshift64* generator, seeded from the standard library's
+// random hasher keys. Fast rather than cryptographically secure.
+fn random_u64() -> u64 {
+    thread_local! {
+        static STATE: Cell<u64> = Cell::new(RandomState::new().build_hasher().finish() | 1);
     }
+    STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.set(x);
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    })
+}
+
+pub fn decode(id: &str) -> Result<Components, DecodeError> {
+    id.parse::<FlakeId>().map(|flake_id| flake_id.components())
 }
 
 pub fn get_non_loopback_address() -> [u8; 6] {
-    let interfaces = interfaces::Interface::get_all();
-    match interfaces {
-        Ok(vector) => {
-            for interface in vector {
-                if !interface.is_loopback() && interface.is_up() {
-                    let hardware_addr = interface.hardware_addr().unwrap();
+    match try_get_non_loopback_address() {
+        Ok(bytes) => bytes,
+        Err(e) => panic!("{}", e),
+    }
+}
+
+pub fn try_get_non_loopback_address() -> Result<[u8; 6], Error> {
+    let interfaces = interfaces::Interface::get_all().map_err(Error::InterfaceEnumeration)?;
+    let mut without_hardware_addr = None;
+    for interface in interfaces {
+        if !interface.is_loopback() && interface.is_up() {
+            match interface.hardware_addr() {
+                Ok(hardware_addr) => {
                     let mut bytes = [0; 6];
-                    bytes[..6].clone_from_slice(&hardware_addr.as_bytes());
-                    return bytes;
+                    bytes[..6].clone_from_slice(hardware_addr.as_bytes());
+                    return Ok(bytes);
                 }
+                Err(_e) => without_hardware_addr = Some(interface.name.clone()),
             }
-            panic!("Can't find an suitable interface address")
         }
-        Err(_e) => panic!("Error retrieving interfaces"),
+    }
+    match without_hardware_addr {
+        Some(name) => Err(Error::NoHardwareAddress(name)),
+        None => Err(Error::NoInterface),
     }
 }
 
@@ -100,7 +442,40 @@ mod tests {
 
     use super::*;
     use std::collections::HashSet;
-    use test::Bencher;
+    use std::sync::Mutex;
+
+    pub(crate) fn manual_generator(seed: [u8; 6]) -> (Generator, ManualClock) {
+        let clock = ManualClock::new(UNIX_EPOCH + Duration::from_millis(1_546_300_800_000));
+        (Generator::with_seed(seed).with_clock(clock.clone()), clock)
+    }
+
+    // A 1/41/10/12 layout, whose sequence runs out after 4096 ids a tick.
+    pub(crate) fn narrow_generator() -> (Generator, ManualClock) {
+        let layout = Layout::builder()
+            .reserved(1)
+            .timestamp(41)
+            .node(10)
+            .sequence(12)
+            .build()
+            .unwrap();
+        let clock = ManualClock::new(UNIX_EPOCH + Duration::from_millis(1_546_300_800_000));
+        let generator = Generator::builder()
+            .seed([1; 6])
+            .clock(clock.clone())
+            .layout(layout)
+            .build()
+            .unwrap();
+        (generator, clock)
+    }
+
+    // Advances `clock` once the caller has had time to start waiting on it.
+    pub(crate) fn advance_later(clock: &ManualClock, by: Duration) -> thread::JoinHandle<()> {
+        let clock = clock.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            clock.advance(by);
+        })
+    }
 
     #[test]
     fn test_with_seed() {
@@ -108,12 +483,57 @@ mod tests {
             Generator::with_seed([0; 6]),
             Generator {
                 seed: [0; 6],
-                sequence: AtomicU64::new(0),
-                timestamp: AtomicU64::new(0)
+                state: State::new(0, 0),
+                floor: 0,
+                epoch: UNIX_EPOCH,
+                time_unit: TimeUnit::Millis,
+                encoding: Encoding::Base64Url,
+                clock: Arc::new(SystemClock),
+                rollback: ClockRollback::KeepLast,
+                clock_skew: AtomicU64::new(0),
+                sequence_mode: SequenceMode::Continuous,
+                layout: Layout::default(),
+                shard: 0,
+                checkpoint: None,
             }
         );
     }
 
+    #[test]
+    fn test_try_new_matches_non_loopback_address() {
+        match try_get_non_loopback_address() {
+            Ok(seed) => assert_eq!(Generator::try_new().unwrap(), Generator::with_seed(seed)),
+            Err(_) => assert!(Generator::try_new().is_err()),
+        }
+    }
+
+    #[test]
+    fn test_with_provider() {
+        let seed = [9, 8, 7, 6, 5, 4];
+        assert_eq!(
+            Generator::with_provider(&ExplicitSeed(seed)).unwrap(),
+            Generator::with_seed(seed)
+        );
+        assert!(Generator::with_provider(&EnvSeed::new("RUSTFLAKE_TEST_UNSET_SEED")).is_err());
+    }
+
+    #[test]
+    fn test_generate_str_and_into_match_generate() {
+        let (generator, _clock) = manual_generator([1; 6]);
+        let first = generator.generate();
+        let second = generator.generate_str();
+        let mut buf = [0; 20];
+        let third = generator.generate_into(&mut buf).unwrap().to_string();
+        assert_eq!(second.len(), first.len());
+        assert!(first.parse::<FlakeId>().unwrap() < second.parse::<FlakeId>().unwrap());
+        assert_eq!(third.parse::<FlakeId>().unwrap().sequence(), 2);
+        assert!(matches!(
+            generator.generate_into(&mut [0; 19]),
+            Err(Error::BufferTooSmall(20))
+        ));
+        assert_eq!(generator.generate_id().sequence(), 3);
+    }
+
     #[test]
     fn test_generate_value() {
         let generator = Generator::new();
@@ -121,9 +541,52 @@ mod tests {
         assert!(decoded.is_ok())
     }
 
+    #[test]
+    fn test_generate_id_embeds_seed() {
+        let seed = [1, 2, 3, 4, 5, 6];
+        let generator = Generator::with_seed(seed);
+        let id = generator.generate_id();
+        assert_eq!(id.as_bytes()[6..12], seed);
+    }
+
+    #[test]
+    fn test_subsequent_generate_ids_are_greater() {
+        let generator = Generator::with_seed([0; 6]);
+        let first_id = generator.generate_id();
+        let second_id = generator.generate_id();
+        assert!(first_id < second_id);
+    }
+
+    #[test]
+    fn test_decode_generated_value() {
+        let seed = [0xde, 0xad, 0xbe, 0xef, 0x00, 0x01];
+        let generator = Generator::with_seed(seed);
+        let before = SystemTime::now();
+        generator.generate();
+        let components = decode(&generator.generate()).unwrap();
+        assert_eq!(components.seed, seed);
+        assert_eq!(components.sequence, 1);
+        let before_in_ms = before.duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+        let decoded_in_ms = components
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        assert!(decoded_in_ms >= before_in_ms);
+    }
+
+    #[test]
+    fn test_decode_invalid_value() {
+        assert_eq!(decode("too-short"), Err(DecodeError::InvalidLength(9)));
+        assert_eq!(
+            decode("AAAAAAAAAA*AAAAAAAAA"),
+            Err(DecodeError::InvalidCharacter(10, b'*'))
+        );
+    }
+
     #[test]
     fn test_subsequent_generate_lexically_greater_values() {
-        let generator = Generator::new();
+        let generator = Generator::new().with_encoding(Encoding::Base64Sortable);
         let first_value = generator.generate();
         let second_value = generator.generate();
         assert!(
@@ -147,20 +610,292 @@ mod tests {
         }
     }
 
-    #[bench]
-    fn bench_generator(b: &mut Bencher) {
-        let generator = Generator::new();
-        b.iter(|| generator.generate());
+    #[test]
+    fn test_sortable_values_across_seeds_and_sequences() {
+        let generator = Generator::with_seed([0xff, 0xfe, 0xcc, 0xd0, 0x3f, 0x40])
+            .with_encoding(Encoding::Base64Sortable);
+        let mut previous = generator.generate();
+        for _x in 0..10000 {
+            let next = generator.generate();
+            assert!(previous < next, "{} >= {}", previous, next);
+            previous = next;
+        }
     }
 
-    #[bench]
-    fn bench_generator_100000(b: &mut Bencher) {
-        let generator = test::black_box(Generator::new());
-        b.iter(|| {
-            for _x in 0..100000 {
-                generator.generate();
-            }
-        });
+    #[test]
+    fn test_generate_id_uses_clock() {
+        let (generator, clock) = manual_generator([1; 6]);
+        let id = generator.generate_id();
+        assert_eq!(id.timestamp(), clock.now());
+        assert_eq!(id.sequence(), 0);
     }
 
+    #[test]
+    fn test_generate_id_within_same_millisecond() {
+        let (generator, clock) = manual_generator([1; 6]);
+        clock.advance(Duration::from_micros(300));
+        let first_id = generator.generate_id();
+        clock.advance(Duration::from_micros(600));
+        let second_id = generator.generate_id();
+        assert_eq!(first_id.timestamp(), second_id.timestamp());
+        assert_eq!(second_id.sequence(), first_id.sequence() + 1);
+        assert!(first_id < second_id);
+    }
+
+    #[test]
+    fn test_generate_id_across_millisecond_boundary() {
+        let (generator, clock) = manual_generator([1; 6]);
+        let first_id = generator.generate_id();
+        clock.advance(Duration::from_millis(1));
+        let second_id = generator.generate_id();
+        assert_eq!(
+            second_id.timestamp(),
+            first_id.timestamp() + Duration::from_millis(1)
+        );
+        assert!(first_id < second_id);
+    }
+
+    #[test]
+    fn test_generate_id_keeps_timestamp_when_clock_goes_backwards() {
+        let (generator, clock) = manual_generator([1; 6]);
+        let first_id = generator.generate_id();
+        clock.rewind(Duration::from_secs(10));
+        let second_id = generator.generate_id();
+        assert_eq!(second_id.timestamp(), first_id.timestamp());
+        assert!(first_id < second_id);
+    }
+
+    #[test]
+    fn test_keep_last_records_clock_skew() {
+        let (generator, clock) = manual_generator([1; 6]);
+        generator.generate_id();
+        clock.rewind(Duration::from_millis(250));
+        assert!(generator.try_generate_id().is_ok());
+        clock.rewind(Duration::from_millis(50));
+        generator.generate_id();
+        clock.advance(Duration::from_millis(200));
+        generator.generate_id();
+        assert_eq!(generator.clock_skew(), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_fail_beyond_threshold() {
+        let (generator, clock) = manual_generator([1; 6]);
+        let generator =
+            generator.with_clock_rollback(ClockRollback::Fail(Duration::from_millis(5)));
+        let first_id = generator.generate_id();
+        clock.rewind(Duration::from_millis(5));
+        assert_eq!(
+            generator.try_generate_id().unwrap().timestamp(),
+            first_id.timestamp()
+        );
+        clock.rewind(Duration::from_millis(1));
+        match generator.try_generate() {
+            Err(Error::ClockMovedBackwards(skew)) => assert_eq!(skew, Duration::from_millis(6)),
+            other => panic!("Expected ClockMovedBackwards, got {:?}", other),
+        }
+        clock.advance(Duration::from_millis(7));
+        assert!(generator.try_generate_id().unwrap() > first_id);
+    }
+
+    #[test]
+    fn test_notify_reports_skew() {
+        let observed = Arc::new(Mutex::new(Vec::new()));
+        let reported = observed.clone();
+        let (generator, clock) = manual_generator([1; 6]);
+        let generator =
+            generator.with_clock_rollback(ClockRollback::Notify(Arc::new(move |skew| {
+                reported.lock().unwrap().push(skew)
+            })));
+        generator.generate_id();
+        clock.rewind(Duration::from_millis(20));
+        generator.generate_id();
+        assert_eq!(*observed.lock().unwrap(), vec![Duration::from_millis(20)]);
+    }
+
+    #[test]
+    fn test_notify_fires_once_per_id_under_contention() {
+        let observed = Arc::new(AtomicU64::new(0));
+        let reported = observed.clone();
+        let (generator, clock) = manual_generator([1; 6]);
+        let generator = Arc::new(
+            generator.with_clock_rollback(ClockRollback::Notify(Arc::new(move |_skew| {
+                reported.fetch_add(1, Ordering::Relaxed);
+            }))),
+        );
+        generator.generate_id();
+        clock.rewind(Duration::from_millis(20));
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let generator = generator.clone();
+                thread::spawn(move || {
+                    for _ in 0..1000 {
+                        generator.generate_id();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(observed.load(Ordering::Relaxed), 8000);
+    }
+
+    #[test]
+    fn test_block_waits_for_clock_to_catch_up() {
+        let (generator, clock) = manual_generator([1; 6]);
+        let generator = generator.with_clock_rollback(ClockRollback::Block);
+        let first_id = generator.generate_id();
+        clock.rewind(Duration::from_millis(3));
+        let handle = advance_later(&clock, Duration::from_millis(4));
+        let second_id = generator.generate_id();
+        handle.join().unwrap();
+        assert_eq!(
+            second_id.timestamp(),
+            first_id.timestamp() + Duration::from_millis(1)
+        );
+    }
+
+    #[test]
+    fn test_clock_before_unix_epoch_is_a_rollback() {
+        let (generator, clock) = manual_generator([1; 6]);
+        let generator = generator.with_clock_rollback(ClockRollback::Fail(Duration::from_secs(1)));
+        generator.generate_id();
+        clock.set(UNIX_EPOCH - Duration::from_secs(1));
+        assert!(matches!(
+            generator.try_generate_id(),
+            Err(Error::ClockMovedBackwards(_))
+        ));
+    }
+
+    #[test]
+    fn test_ids_are_monotonic_across_threads() {
+        let generator = Arc::new(Generator::with_seed([1; 6]));
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let generator = generator.clone();
+                thread::spawn(move || {
+                    let ids: Vec<FlakeId> = (0..20000).map(|_| generator.generate_id()).collect();
+                    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+                    ids
+                })
+            })
+            .collect();
+        let mut ids: Vec<FlakeId> = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect();
+        // The sequence records the order in which ids were issued; sorted by
+        // it, no id may carry an earlier timestamp than the one before it.
+        ids.sort_by_key(FlakeId::sequence);
+        assert!(ids
+            .windows(2)
+            .all(|pair| pair[0].sequence() + 1 == pair[1].sequence() && pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_timestamp_overflow() {
+        let (generator, clock) = manual_generator([1; 6]);
+        let max_timestamp = Layout::default().max_value(Field::Timestamp);
+        clock.set(UNIX_EPOCH + Duration::from_millis(max_timestamp + 1));
+        assert!(matches!(
+            generator.try_generate_id(),
+            Err(Error::TimestampOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_sequence_wraps_after_24_bits_in_the_next_tick() {
+        let (generator, clock) = manual_generator([1; 6]);
+        let first_id = generator.generate_id();
+        generator.state.store(
+            TimeUnit::Millis.ticks_since(UNIX_EPOCH, first_id.timestamp()),
+            (1 << 24) - 1,
+        );
+        assert_eq!(generator.generate_id().sequence(), (1 << 24) - 1);
+        let handle = advance_later(&clock, Duration::from_millis(1));
+        let id = generator.generate_id();
+        handle.join().unwrap();
+        assert_eq!(id.sequence(), 0);
+        assert_eq!(
+            id.timestamp(),
+            first_id.timestamp() + Duration::from_millis(1)
+        );
+    }
+
+    #[test]
+    fn test_narrow_sequence_never_repeats_within_a_tick() {
+        let (generator, clock) = narrow_generator();
+        let layout = generator.layout();
+        let mut set = HashSet::new();
+        for _ in 0..4096 {
+            assert!(set.insert(generator.generate_id()));
+        }
+        let handle = advance_later(&clock, Duration::from_millis(1));
+        let id = generator.generate_id();
+        handle.join().unwrap();
+        assert!(id > *set.iter().max().unwrap());
+        assert_eq!(layout.value(&id, Field::Sequence), 0);
+        assert_eq!(generator.decoder().components(&id).timestamp, clock.now());
+    }
+
+    #[test]
+    fn test_reset_per_tick_restarts_sequence() {
+        let (generator, clock) = manual_generator([1; 6]);
+        let generator = generator.with_sequence_mode(SequenceMode::ResetPerTick(OnExhausted::Fail));
+        assert_eq!(generator.generate_id().sequence(), 0);
+        assert_eq!(generator.generate_id().sequence(), 1);
+        clock.advance(Duration::from_millis(1));
+        let id = generator.generate_id();
+        assert_eq!(id.sequence(), 0);
+        assert_eq!(id.timestamp(), clock.now());
+    }
+
+    #[test]
+    fn test_reset_per_tick_keeps_counting_when_clock_goes_backwards() {
+        let (generator, clock) = manual_generator([1; 6]);
+        let generator = generator.with_sequence_mode(SequenceMode::ResetPerTick(OnExhausted::Fail));
+        let first_id = generator.generate_id();
+        clock.rewind(Duration::from_millis(3));
+        let second_id = generator.generate_id();
+        assert_eq!(second_id.timestamp(), first_id.timestamp());
+        assert_eq!(second_id.sequence(), 1);
+    }
+
+    #[test]
+    fn test_reset_per_tick_fails_when_exhausted() {
+        let (generator, clock) = manual_generator([1; 6]);
+        let generator = generator.with_sequence_mode(SequenceMode::ResetPerTick(OnExhausted::Fail));
+        let first_id = generator.generate_id();
+        let timestamp = TimeUnit::Millis.ticks_since(UNIX_EPOCH, first_id.timestamp());
+        let max_sequence = generator.layout().max_value(Field::Sequence);
+        generator.state.store(timestamp, max_sequence);
+        assert_eq!(generator.generate_id().sequence() as u64, max_sequence);
+        assert!(matches!(
+            generator.try_generate_id(),
+            Err(Error::SequenceExhausted)
+        ));
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(generator.generate_id().sequence(), 0);
+    }
+
+    #[test]
+    fn test_reset_per_tick_waits_for_next_tick_when_exhausted() {
+        let (generator, clock) = manual_generator([1; 6]);
+        let generator =
+            generator.with_sequence_mode(SequenceMode::ResetPerTick(OnExhausted::WaitForNextTick));
+        let first_id = generator.generate_id();
+        generator.state.store(
+            TimeUnit::Millis.ticks_since(UNIX_EPOCH, first_id.timestamp()),
+            generator.layout().max_value(Field::Sequence) + 1,
+        );
+        let handle = advance_later(&clock, Duration::from_millis(1));
+        let id = generator.generate_id();
+        handle.join().unwrap();
+        assert_eq!(id.sequence(), 0);
+        assert_eq!(
+            id.timestamp(),
+            first_id.timestamp() + Duration::from_millis(1)
+        );
+    }
 }
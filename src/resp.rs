@@ -0,0 +1,344 @@
+//! A Redis-protocol (RESP) id server, behind the `resp` feature, so that
+//! existing Redis clients can fetch ids. It answers `PING`,
+//! `FLAKE.NEXT`, `FLAKE.BATCH count`, `FLAKE.DECODE id` and `QUIT`.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+
+use crate::server::{invalid, serve_connections};
+use crate::{Generator, MAX_REQUEST_IDS};
+
+// Limits on a single request, so a bad client can't make us allocate
+// without bound.
+const MAX_ARGS: usize = 16;
+const MAX_BULK_LEN: usize = 4096;
+
+/// Accepts connections on `listener` forever, serving each on its own
+/// thread. A failed accept, e.g. from running out of file descriptors, is
+/// logged and retried rather than stopping the server.
+pub fn serve(generator: Arc<Generator>, listener: TcpListener) -> ! {
+    serve_connections(generator, listener, handle)
+}
+
+fn handle(generator: &Generator, stream: TcpStream) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    let mut reply = Vec::new();
+    loop {
+        reply.clear();
+        let args = match read_command(&mut reader) {
+            Ok(Some(args)) => args,
+            Ok(None) => return Ok(()),
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidData => {
+                Reply::Error(format!("ERR Protocol error: {}", e)).write(&mut reply);
+                return writer.write_all(&reply);
+            }
+            Err(e) => return Err(e),
+        };
+        if args.is_empty() {
+            continue;
+        }
+        let quit = args[0].eq_ignore_ascii_case("QUIT");
+        execute(generator, &args).write(&mut reply);
+        writer.write_all(&reply)?;
+        if quit {
+            return Ok(());
+        }
+    }
+}
+
+enum Reply {
+    Simple(&'static str),
+    Error(String),
+    Integer(u64),
+    Bulk(String),
+    Array(Vec<Reply>),
+}
+
+impl Reply {
+    fn write(&self, out: &mut Vec<u8>) {
+        match *self {
+            Reply::Simple(status) => out.extend_from_slice(format!("+{}\r\n", status).as_bytes()),
+            Reply::Error(ref message) => {
+                out.extend_from_slice(format!("-{}\r\n", message).as_bytes())
+            }
+            Reply::Integer(value) => out.extend_from_slice(format!(":{}\r\n", value).as_bytes()),
+            Reply::Bulk(ref value) => {
+                out.extend_from_slice(format!("${}\r\n{}\r\n", value.len(), value).as_bytes())
+            }
+            Reply::Array(ref replies) => {
+                out.extend_from_slice(format!("*{}\r\n", replies.len()).as_bytes());
+                for reply in replies {
+                    reply.write(out);
+                }
+            }
+        }
+    }
+}
+
+fn execute(generator: &Generator, args: &[String]) -> Reply {
+    let command = args[0].to_ascii_uppercase();
+    let arity = match command.as_str() {
+        "PING" => 1..=2,
+        "QUIT" | "FLAKE.NEXT" => 1..=1,
+        "FLAKE.BATCH" | "FLAKE.DECODE" => 2..=2,
+        // Sent by redis-cli on connect.
+        "COMMAND" => return Reply::Array(Vec::new()),
+        _ => return Reply::Error(format!("ERR unknown command '{}'", args[0])),
+    };
+    if !arity.contains(&args.len()) {
+        return Reply::Error(format!(
+            "ERR wrong number of arguments for '{}' command",
+            args[0].to_ascii_lowercase()
+        ));
+    }
+    match command.as_str() {
+        "PING" if args.len() == 2 => Reply::Bulk(args[1].clone()),
+        "PING" => Reply::Simple("PONG"),
+        "QUIT" => Reply::Simple("OK"),
+        "FLAKE.NEXT" => match generator.try_generate() {
+            Ok(id) => Reply::Bulk(id),
+            Err(e) => Reply::Error(format!("ERR {}", e)),
+        },
+        "FLAKE.BATCH" => {
+            let count = match args[1].parse::<usize>() {
+                Ok(count) if count > 0 && count <= MAX_REQUEST_IDS => count,
+                _ => {
+                    return Reply::Error(format!(
+                        "ERR count must be between 1 and {}",
+                        MAX_REQUEST_IDS
+                    ))
+                }
+            };
+            match generator.generate_batch(count) {
+                Ok(ids) => Reply::Array(ids.map(Reply::Bulk).collect()),
+                Err(e) => Reply::Error(format!("ERR {}", e)),
+            }
+        }
+        _ => match generator.decoder().decode(&args[1]) {
+            Ok(components) => Reply::Array(vec![
+                Reply::Bulk("timestamp_ms".to_string()),
+                Reply::Integer(components.timestamp_ms()),
+                Reply::Bulk("seed".to_string()),
+                Reply::Bulk(components.seed_hex()),
+                Reply::Bulk("sequence".to_string()),
+                Reply::Integer(u64::from(components.sequence)),
+            ]),
+            Err(e) => Reply::Error(format!("ERR {}", e)),
+        },
+    }
+}
+
+/// Reads one command, either a RESP array of bulk strings or an inline
+/// command as typed into telnet. Returns `None` at end of stream.
+fn read_command<R: BufRead>(reader: &mut R) -> io::Result<Option<Vec<String>>> {
+    let line = match read_line(reader)? {
+        Some(line) => line,
+        None => return Ok(None),
+    };
+    if !line.starts_with('*') {
+        return Ok(Some(line.split_whitespace().map(String::from).collect()));
+    }
+    let count = parse_len(&line[1..], MAX_ARGS)?;
+    let mut args = Vec::with_capacity(count);
+    for _ in 0..count {
+        let header = read_line(reader)?.ok_or_else(|| invalid("unexpected end of stream"))?;
+        if !header.starts_with('$') {
+            return Err(invalid("expected '$'"));
+        }
+        let len = parse_len(&header[1..], MAX_BULK_LEN)?;
+        let mut bulk = vec![0; len + 2];
+        reader.read_exact(&mut bulk)?;
+        if !bulk.ends_with(b"\r\n") {
+            return Err(invalid("bulk string not terminated by CRLF"));
+        }
+        bulk.truncate(len);
+        args.push(String::from_utf8(bulk).map_err(|_e| invalid("bulk string is not UTF-8"))?);
+    }
+    Ok(Some(args))
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    reader
+        .by_ref()
+        .take(MAX_BULK_LEN as u64)
+        .read_until(b'\n', &mut line)?;
+    if line.is_empty() {
+        return Ok(None);
+    }
+    if !line.ends_with(b"\n") {
+        return Err(invalid("line too long"));
+    }
+    let line = String::from_utf8(line).map_err(|_e| invalid("line is not UTF-8"))?;
+    Ok(Some(line.trim_end_matches(&['\r', '\n'][..]).to_string()))
+}
+
+fn parse_len(value: &str, max: usize) -> io::Result<usize> {
+    match value.parse::<usize>() {
+        Ok(len) if len <= max => Ok(len),
+        _ => Err(invalid("invalid length")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::SnowFlaker;
+    use std::net::SocketAddr;
+    use std::thread;
+
+    /// Just enough of a RESP client to check replies.
+    #[derive(Debug, PartialEq)]
+    enum Value {
+        Simple(String),
+        Error(String),
+        Integer(i64),
+        Bulk(String),
+        Array(Vec<Value>),
+    }
+
+    struct Client {
+        reader: BufReader<TcpStream>,
+        writer: TcpStream,
+    }
+
+    impl Client {
+        fn connect(addr: SocketAddr) -> Client {
+            let stream = TcpStream::connect(addr).unwrap();
+            Client {
+                reader: BufReader::new(stream.try_clone().unwrap()),
+                writer: stream,
+            }
+        }
+
+        fn command(&mut self, args: &[&str]) -> Value {
+            let mut request = format!("*{}\r\n", args.len());
+            for arg in args {
+                request.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+            }
+            self.send(request.as_bytes())
+        }
+
+        fn send(&mut self, request: &[u8]) -> Value {
+            self.writer.write_all(request).unwrap();
+            self.read()
+        }
+
+        fn read(&mut self) -> Value {
+            let mut line = String::new();
+            self.reader.read_line(&mut line).unwrap();
+            let (kind, rest) = line.trim_end().split_at(1);
+            match kind {
+                "+" => Value::Simple(rest.to_string()),
+                "-" => Value::Error(rest.to_string()),
+                ":" => Value::Integer(rest.parse().unwrap()),
+                "$" => {
+                    let mut bulk = vec![0; rest.parse::<usize>().unwrap() + 2];
+                    self.reader.read_exact(&mut bulk).unwrap();
+                    bulk.truncate(bulk.len() - 2);
+                    Value::Bulk(String::from_utf8(bulk).unwrap())
+                }
+                "*" => Value::Array((0..rest.parse().unwrap()).map(|_| self.read()).collect()),
+                _ => panic!("unexpected reply {:?}", line),
+            }
+        }
+    }
+
+    fn start() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let generator = Arc::new(Generator::with_seed([1, 2, 3, 4, 5, 6]));
+        thread::spawn(move || serve(generator, listener));
+        addr
+    }
+
+    #[test]
+    fn test_ping() {
+        let mut client = Client::connect(start());
+        assert_eq!(client.command(&["PING"]), Value::Simple("PONG".to_string()));
+        assert_eq!(
+            client.command(&["ping", "hello"]),
+            Value::Bulk("hello".to_string())
+        );
+        assert_eq!(client.send(b"PING\r\n"), Value::Simple("PONG".to_string()));
+    }
+
+    #[test]
+    fn test_next_and_batch() {
+        let mut client = Client::connect(start());
+        let first = match client.command(&["FLAKE.NEXT"]) {
+            Value::Bulk(id) => id.parse::<crate::FlakeId>().unwrap(),
+            reply => panic!("unexpected reply {:?}", reply),
+        };
+        assert_eq!(first.seed(), [1, 2, 3, 4, 5, 6]);
+        let ids = match client.command(&["flake.batch", "3"]) {
+            Value::Array(ids) => ids,
+            reply => panic!("unexpected reply {:?}", reply),
+        };
+        assert_eq!(ids.len(), 3);
+        for (i, id) in ids.into_iter().enumerate() {
+            match id {
+                Value::Bulk(id) => {
+                    assert_eq!(
+                        id.parse::<crate::FlakeId>().unwrap().sequence(),
+                        i as u32 + 1
+                    )
+                }
+                reply => panic!("unexpected reply {:?}", reply),
+            }
+        }
+        assert!(matches!(
+            client.command(&["FLAKE.BATCH", "0"]),
+            Value::Error(_)
+        ));
+    }
+
+    #[test]
+    fn test_decode() {
+        let mut client = Client::connect(start());
+        assert_eq!(
+            client.command(&["FLAKE.DECODE", "AWgGtbwAAQIDBAUGAAAH"]),
+            Value::Array(vec![
+                Value::Bulk("timestamp_ms".to_string()),
+                Value::Integer(1_546_300_800_000),
+                Value::Bulk("seed".to_string()),
+                Value::Bulk("01:02:03:04:05:06".to_string()),
+                Value::Bulk("sequence".to_string()),
+                Value::Integer(7),
+            ])
+        );
+        assert!(matches!(
+            client.command(&["FLAKE.DECODE", "nope"]),
+            Value::Error(_)
+        ));
+    }
+
+    #[test]
+    fn test_errors_and_quit() {
+        let mut client = Client::connect(start());
+        assert_eq!(
+            client.command(&["GET", "key"]),
+            Value::Error("ERR unknown command 'GET'".to_string())
+        );
+        assert_eq!(
+            client.command(&["FLAKE.NEXT", "extra"]),
+            Value::Error("ERR wrong number of arguments for 'flake.next' command".to_string())
+        );
+        assert_eq!(client.command(&["QUIT"]), Value::Simple("OK".to_string()));
+        let mut rest = Vec::new();
+        client.reader.read_to_end(&mut rest).unwrap();
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn test_protocol_error_closes_connection() {
+        let mut client = Client::connect(start());
+        assert!(matches!(
+            client.send(b"*1\r\n$x\r\n"),
+            Value::Error(ref message) if message.starts_with("ERR Protocol error")
+        ));
+    }
+}
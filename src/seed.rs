@@ -0,0 +1,251 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::error::Error;
+use crate::try_get_non_loopback_address;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+// The multicast bit of the first octet never appears in a real interface
+// address, so derived seeds can't collide with a MAC-based one.
+const MULTICAST_BIT: u8 = 0x01;
+
+/// Supplies the 6-byte node seed a generator embeds in every id.
+pub trait SeedProvider {
+    fn seed(&self) -> Result<[u8; 6], Error>;
+}
+
+impl SeedProvider for [u8; 6] {
+    fn seed(&self) -> Result<[u8; 6], Error> {
+        Ok(*self)
+    }
+}
+
+/// The hardware address of the first up, non-loopback interface.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MacAddressSeed;
+
+impl SeedProvider for MacAddressSeed {
+    fn seed(&self) -> Result<[u8; 6], Error> {
+        try_get_non_loopback_address()
+    }
+}
+
+/// A hash of the host name, stable across restarts of the same host.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HostnameSeed;
+
+impl SeedProvider for HostnameSeed {
+    fn seed(&self) -> Result<[u8; 6], Error> {
+        let hostname = hostname::get().map_err(Error::Io)?;
+        Ok(hash_seed(hostname.to_string_lossy().as_bytes()))
+    }
+}
+
+/// A seed read from an environment variable in `parse_seed` format.
+#[derive(Debug, Clone)]
+pub struct EnvSeed(String);
+
+impl EnvSeed {
+    pub fn new<S: Into<String>>(name: S) -> EnvSeed {
+        EnvSeed(name.into())
+    }
+}
+
+impl SeedProvider for EnvSeed {
+    fn seed(&self) -> Result<[u8; 6], Error> {
+        let value = env::var(&self.0).map_err(|_e| Error::MissingEnvVar(self.0.clone()))?;
+        parse_seed(&value)
+    }
+}
+
+/// A fixed seed, typically handed out by an orchestrator.
+#[derive(Debug, Clone, Copy)]
+pub struct ExplicitSeed(pub [u8; 6]);
+
+impl SeedProvider for ExplicitSeed {
+    fn seed(&self) -> Result<[u8; 6], Error> {
+        Ok(self.0)
+    }
+}
+
+/// A random seed drawn once per process and shared by every generator in it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomSeed;
+
+impl SeedProvider for RandomSeed {
+    fn seed(&self) -> Result<[u8; 6], Error> {
+        static SEED: OnceLock<[u8; 6]> = OnceLock::new();
+        if let Some(seed) = SEED.get() {
+            return Ok(*seed);
+        }
+        let mut seed = [0; 6];
+        getrandom::getrandom(&mut seed).map_err(Error::Random)?;
+        seed[0] |= MULTICAST_BIT;
+        Ok(*SEED.get_or_init(|| seed))
+    }
+}
+
+/// A seed stored on disk, either as 6 raw bytes or in `parse_seed` format.
+#[derive(Debug, Clone)]
+pub struct FileSeed(PathBuf);
+
+impl FileSeed {
+    pub fn new<P: Into<PathBuf>>(path: P) -> FileSeed {
+        FileSeed(path.into())
+    }
+}
+
+impl SeedProvider for FileSeed {
+    fn seed(&self) -> Result<[u8; 6], Error> {
+        let contents = fs::read(&self.0).map_err(Error::Io)?;
+        if contents.len() == 6 {
+            let mut seed = [0; 6];
+            seed.copy_from_slice(&contents);
+            return Ok(seed);
+        }
+        let text = String::from_utf8_lossy(&contents);
+        parse_seed(text.trim())
+    }
+}
+
+/// Resolves a seed from a source named on a command line or in config:
+/// `mac`, `hostname`, `random`, `env:NAME`, `file:PATH` or a seed in
+/// `parse_seed` format.
+pub fn resolve_seed(source: &str) -> Result<[u8; 6], Error> {
+    match source {
+        "mac" => MacAddressSeed.seed(),
+        "hostname" => HostnameSeed.seed(),
+        "random" => RandomSeed.seed(),
+        _ if source.starts_with("env:") => EnvSeed::new(&source[4..]).seed(),
+        _ if source.starts_with("file:") => FileSeed::new(&source[5..]).seed(),
+        _ => parse_seed(source),
+    }
+}
+
+/// Parses a seed written as a MAC address (`01:23:45:67:89:ab` or
+/// `01-23-45-67-89-ab`) or as 12 hex digits.
+pub fn parse_seed(value: &str) -> Result<[u8; 6], Error> {
+    let digits: Vec<u8> = value.bytes().filter(|&c| c != b':' && c != b'-').collect();
+    if digits.len() != 12 || !digits.iter().all(u8::is_ascii_hexdigit) {
+        return Err(Error::InvalidSeed(value.to_string()));
+    }
+    let mut seed = [0; 6];
+    for (byte, pair) in seed.iter_mut().zip(digits.chunks(2)) {
+        *byte = (hex_value(pair[0]) << 4) | hex_value(pair[1]);
+    }
+    Ok(seed)
+}
+
+fn hex_value(digit: u8) -> u8 {
+    match digit {
+        b'0'..=b'9' => digit - b'0',
+        b'a'..=b'f' => digit - b'a' + 10,
+        _ => digit - b'A' + 10,
+    }
+}
+
+fn hash_seed(bytes: &[u8]) -> [u8; 6] {
+    let hash = bytes.iter().fold(FNV_OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    });
+    let mut seed = [0; 6];
+    crate::put_uint(&mut seed, hash ^ (hash >> 48), 0, 6);
+    seed[0] |= MULTICAST_BIT;
+    seed
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_parse_seed_formats() {
+        let expected = [0x01, 0x23, 0x45, 0x67, 0x89, 0xab];
+        assert_eq!(parse_seed("01:23:45:67:89:ab").unwrap(), expected);
+        assert_eq!(parse_seed("01-23-45-67-89-AB").unwrap(), expected);
+        assert_eq!(parse_seed("0123456789ab").unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse_seed_rejects_invalid_values() {
+        assert!(matches!(parse_seed("01:23:45"), Err(Error::InvalidSeed(_))));
+        assert!(matches!(
+            parse_seed("01:23:45:67:89:zz"),
+            Err(Error::InvalidSeed(_))
+        ));
+        assert!(matches!(
+            parse_seed("+1+2+3+4+5+6"),
+            Err(Error::InvalidSeed(_))
+        ));
+    }
+
+    #[test]
+    fn test_resolve_seed() {
+        assert_eq!(
+            resolve_seed("01:23:45:67:89:ab").unwrap(),
+            [0x01, 0x23, 0x45, 0x67, 0x89, 0xab]
+        );
+        assert_eq!(resolve_seed("random").unwrap(), RandomSeed.seed().unwrap());
+        assert!(matches!(
+            resolve_seed("env:RUSTFLAKE_TEST_RESOLVE_UNSET"),
+            Err(Error::MissingEnvVar(_))
+        ));
+        assert!(matches!(resolve_seed("nic"), Err(Error::InvalidSeed(_))));
+    }
+
+    #[test]
+    fn test_explicit_seed() {
+        let seed = [1, 2, 3, 4, 5, 6];
+        assert_eq!(ExplicitSeed(seed).seed().unwrap(), seed);
+        assert_eq!(seed.seed().unwrap(), seed);
+    }
+
+    #[test]
+    fn test_env_seed() {
+        env::set_var("RUSTFLAKE_TEST_ENV_SEED", "de:ad:be:ef:00:01");
+        assert_eq!(
+            EnvSeed::new("RUSTFLAKE_TEST_ENV_SEED").seed().unwrap(),
+            [0xde, 0xad, 0xbe, 0xef, 0x00, 0x01]
+        );
+        assert!(matches!(
+            EnvSeed::new("RUSTFLAKE_TEST_ENV_SEED_UNSET").seed(),
+            Err(Error::MissingEnvVar(_))
+        ));
+    }
+
+    #[test]
+    fn test_file_seed() {
+        let dir = env::temp_dir();
+        let raw = dir.join(format!("rustflake-raw-seed-{}", std::process::id()));
+        let text = dir.join(format!("rustflake-text-seed-{}", std::process::id()));
+        fs::write(&raw, [6, 5, 4, 3, 2, 1]).unwrap();
+        fs::write(&text, "06:05:04:03:02:01\n").unwrap();
+        assert_eq!(FileSeed::new(&raw).seed().unwrap(), [6, 5, 4, 3, 2, 1]);
+        assert_eq!(FileSeed::new(&text).seed().unwrap(), [6, 5, 4, 3, 2, 1]);
+        fs::remove_file(raw).unwrap();
+        fs::remove_file(text).unwrap();
+        assert!(matches!(
+            FileSeed::new(dir.join("rustflake-missing-seed")).seed(),
+            Err(Error::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_random_seed_is_stable_within_process() {
+        let seed = RandomSeed.seed().unwrap();
+        assert_eq!(RandomSeed.seed().unwrap(), seed);
+        assert_eq!(seed[0] & MULTICAST_BIT, MULTICAST_BIT);
+    }
+
+    #[test]
+    fn test_hostname_seed_is_deterministic() {
+        assert_eq!(HostnameSeed.seed().unwrap(), HostnameSeed.seed().unwrap());
+        assert_ne!(hash_seed(b"pod-a"), hash_seed(b"pod-b"));
+    }
+}
@@ -0,0 +1,22 @@
+/// How the sequence field advances between ids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SequenceMode {
+    /// One counter for the generator's lifetime. When it wraps, the next id
+    /// waits for a new tick so no timestamp sees the same sequence twice.
+    #[default]
+    Continuous,
+    /// Restart at zero on every new timestamp, so ids can only repeat if a
+    /// single tick runs out of sequence numbers.
+    ResetPerTick(OnExhausted),
+}
+
+/// What `SequenceMode::ResetPerTick` does once a tick has used every
+/// sequence number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnExhausted {
+    /// Spin until the clock reaches the next tick.
+    #[default]
+    WaitForNextTick,
+    /// Fail with `Error::SequenceExhausted`.
+    Fail,
+}
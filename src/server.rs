@@ -0,0 +1,60 @@
+//! The accept loop shared by the TCP servers.
+
+use std::cmp;
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+// The pause after a failed accept doubles from the first value up to the
+// second while failures persist, so running out of file descriptors neither
+// spins nor floods the log.
+const MIN_BACKOFF: Duration = Duration::from_millis(10);
+const MAX_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Accepts connections on `listener` forever, calling `handle` with each on
+/// its own thread. A failed accept is logged to stderr and retried after a
+/// pause, since it usually comes from a client or a passing shortage of
+/// file descriptors rather than a broken listener.
+pub(crate) fn serve_connections<G>(
+    state: Arc<G>,
+    listener: TcpListener,
+    handle: fn(&G, TcpStream) -> io::Result<()>,
+) -> !
+where
+    G: Send + Sync + ?Sized + 'static,
+{
+    let mut backoff = MIN_BACKOFF;
+    loop {
+        match listener.accept() {
+            Ok((stream, _addr)) => {
+                backoff = MIN_BACKOFF;
+                let state = Arc::clone(&state);
+                thread::spawn(move || {
+                    let _ = handle(&state, stream);
+                });
+            }
+            // The client gave up before we got to it.
+            Err(ref e) if aborted(e) => {}
+            Err(e) => {
+                eprintln!("rustflake: accept failed, retrying in {:?}: {}", backoff, e);
+                thread::sleep(backoff);
+                backoff = cmp::min(backoff * 2, MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+fn aborted(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::Interrupted
+    )
+}
+
+pub(crate) fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
@@ -0,0 +1,151 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::error::Error;
+use crate::flake_id::FlakeId;
+use crate::flake_str::FlakeStr;
+use crate::{Generator, SnowFlaker};
+
+static NEXT_THREAD_SLOT: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static THREAD_SLOT: usize = NEXT_THREAD_SLOT.fetch_add(1, Ordering::Relaxed);
+}
+
+// Keeps each generator's state on its own cache lines so that shards used
+// by different threads don't contend through false sharing.
+#[derive(Debug)]
+#[repr(align(128))]
+struct Padded(Generator);
+
+/// A set of generators that differ only in the shard field of their ids, so
+/// ids from different shards never collide. Each thread is assigned its own
+/// shard, round robin, and only shares it with others once there are more
+/// threads than shards. Built with `GeneratorBuilder::build_sharded`.
+#[derive(Debug)]
+pub struct ShardedGenerator {
+    shards: Vec<Padded>,
+}
+
+impl ShardedGenerator {
+    pub(crate) fn new(generators: Vec<Generator>) -> ShardedGenerator {
+        ShardedGenerator {
+            shards: generators.into_iter().map(Padded).collect(),
+        }
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    pub fn shard(&self, index: usize) -> Option<&Generator> {
+        self.shards.get(index).map(|padded| &padded.0)
+    }
+
+    /// The generator assigned to the calling thread.
+    pub fn local(&self) -> &Generator {
+        let slot = THREAD_SLOT.with(|slot| *slot);
+        &self.shards[slot % self.shards.len()].0
+    }
+
+    pub fn generate(&self) -> String {
+        self.local().generate()
+    }
+
+    pub fn generate_id(&self) -> FlakeId {
+        self.local().generate_id()
+    }
+
+    pub fn generate_str(&self) -> FlakeStr {
+        self.local().generate_str()
+    }
+
+    pub fn try_generate(&self) -> Result<String, Error> {
+        self.local().try_generate()
+    }
+
+    pub fn try_generate_id(&self) -> Result<FlakeId, Error> {
+        self.local().try_generate_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::builder::GeneratorBuilder;
+    use crate::layout::{Field, Layout};
+    use std::collections::HashSet;
+    use std::ptr;
+    use std::sync::Arc;
+    use std::thread;
+
+    fn sharded_layout() -> Layout {
+        Layout::builder()
+            .timestamp(48)
+            .shard(8)
+            .node(40)
+            .sequence(24)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_shards_are_numbered_from_builder_shard() {
+        let sharded = GeneratorBuilder::new()
+            .seed([1; 6])
+            .layout(sharded_layout())
+            .shard(10)
+            .build_sharded(4)
+            .unwrap();
+        assert_eq!(sharded.shard_count(), 4);
+        for i in 0..4 {
+            let flake_id = sharded.shard(i).unwrap().generate_id();
+            assert_eq!(
+                sharded_layout().value(&flake_id, Field::Shard),
+                10 + i as u64
+            );
+        }
+        assert!(sharded.shard(4).is_none());
+    }
+
+    #[test]
+    fn test_rejects_shards_beyond_layout() {
+        let result = GeneratorBuilder::new()
+            .seed([1; 6])
+            .layout(sharded_layout())
+            .shard(250)
+            .build_sharded(8);
+        assert!(matches!(result, Err(Error::ShardOutOfRange(257))));
+        let result = GeneratorBuilder::new().seed([1; 6]).build_sharded(2);
+        assert!(matches!(result, Err(Error::ShardOutOfRange(1))));
+    }
+
+    #[test]
+    fn test_ids_are_unique_across_threads() {
+        let sharded = Arc::new(
+            GeneratorBuilder::new()
+                .seed([1; 6])
+                .layout(sharded_layout())
+                .build_sharded(4)
+                .unwrap(),
+        );
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let sharded = Arc::clone(&sharded);
+                thread::spawn(move || {
+                    let ids: Vec<FlakeId> = (0..1000).map(|_| sharded.generate_id()).collect();
+                    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+                    assert!(ptr::eq(sharded.local(), sharded.local()));
+                    ids
+                })
+            })
+            .collect();
+        let mut seen = HashSet::new();
+        for handle in handles {
+            for flake_id in handle.join().unwrap() {
+                assert!(seen.insert(flake_id));
+            }
+        }
+        assert_eq!(seen.len(), 8000);
+    }
+}
@@ -0,0 +1,60 @@
+use portable_atomic::{AtomicU128, Ordering};
+
+/// A timestamp and sequence pair.
+pub(crate) type Stamp = (u64, u64);
+
+/// The latest timestamp and the next sequence of a generator, packed into a
+/// single 128-bit word so that both advance in one compare-and-swap.
+#[derive(Debug)]
+pub(crate) struct State(AtomicU128);
+
+impl State {
+    pub(crate) fn new(timestamp: u64, sequence: u64) -> State {
+        State(AtomicU128::new(pack(timestamp, sequence)))
+    }
+
+    pub(crate) fn load(&self) -> (u64, u64) {
+        unpack(self.0.load(Ordering::SeqCst))
+    }
+
+    #[cfg(test)]
+    pub(crate) fn store(&self, timestamp: u64, sequence: u64) {
+        self.0.store(pack(timestamp, sequence), Ordering::SeqCst)
+    }
+
+    /// Replaces the state with the one `advance` computes from it, retrying
+    /// with a fresh reading whenever another thread got there first.
+    pub(crate) fn update<T, E, F>(&self, mut advance: F) -> Result<T, E>
+    where
+        F: FnMut(u64, u64) -> Result<(Stamp, T), E>,
+    {
+        let mut current = self.0.load(Ordering::SeqCst);
+        loop {
+            let (timestamp, sequence) = unpack(current);
+            let ((next_timestamp, next_sequence), result) = advance(timestamp, sequence)?;
+            match self.0.compare_exchange_weak(
+                current,
+                pack(next_timestamp, next_sequence),
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => return Ok(result),
+                Err(actual) => current = actual,
+            }
+        }
+    }
+}
+
+impl PartialEq for State {
+    fn eq(&self, other: &State) -> bool {
+        self.load() == other.load()
+    }
+}
+
+fn pack(timestamp: u64, sequence: u64) -> u128 {
+    (u128::from(timestamp) << 64) | u128::from(sequence)
+}
+
+fn unpack(state: u128) -> (u64, u64) {
+    ((state >> 64) as u64, state as u64)
+}
@@ -0,0 +1,521 @@
+//! A server for Twitter snowflake's Thrift interface, behind the `thrift`
+//! feature, so that snowflake clients can switch to a `CompactGenerator`
+//! unchanged. It speaks the binary protocol over framed transport, as
+//! snowflake did:
+//!
+//! ```thrift
+//! service Snowflake {
+//!   i64 get_worker_id()
+//!   i64 get_timestamp()
+//!   i64 get_id(1:string useragent)
+//!   i64 get_datacenter_id()
+//! }
+//! ```
+//!
+//! Snowflake splits the 10 worker bits of the default `CompactLayout` into
+//! a 5-bit datacenter id above a 5-bit worker id.
+
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use crate::compact::{CompactGenerator, CompactLayout};
+use crate::error::Error;
+use crate::server::{invalid, serve_connections};
+
+const WORKER_ID_BITS: u8 = 5;
+
+const MAX_FRAME_LEN: usize = 1 << 20;
+
+const VERSION_1: u32 = 0x8001_0000;
+const VERSION_MASK: u32 = 0xffff_0000;
+
+const CALL: u8 = 1;
+const REPLY: u8 = 2;
+const EXCEPTION: u8 = 3;
+const ONEWAY: u8 = 4;
+
+const T_STOP: u8 = 0;
+const T_BOOL: u8 = 2;
+const T_BYTE: u8 = 3;
+const T_DOUBLE: u8 = 4;
+const T_I16: u8 = 6;
+const T_I32: u8 = 8;
+const T_I64: u8 = 10;
+const T_STRING: u8 = 11;
+const T_STRUCT: u8 = 12;
+const T_MAP: u8 = 13;
+const T_SET: u8 = 14;
+const T_LIST: u8 = 15;
+
+// TApplicationException types.
+const UNKNOWN_METHOD: i32 = 1;
+const INTERNAL_ERROR: i32 = 6;
+const PROTOCOL_ERROR: i32 = 7;
+
+/// Combines snowflake's datacenter and worker ids, 5 bits each, into the
+/// worker id of a `CompactGenerator` with the default layout.
+pub fn snowflake_worker_id(datacenter_id: u64, worker_id: u64) -> Result<u64, Error> {
+    let max = (1 << WORKER_ID_BITS) - 1;
+    if datacenter_id > max {
+        return Err(Error::WorkerIdOutOfRange(datacenter_id));
+    }
+    if worker_id > max {
+        return Err(Error::WorkerIdOutOfRange(worker_id));
+    }
+    Ok((datacenter_id << WORKER_ID_BITS) | worker_id)
+}
+
+/// Accepts connections on `listener` forever, serving each on its own
+/// thread. A failed accept is logged and retried rather than stopping the
+/// server. Returns only if the generator doesn't use the default layout.
+pub fn serve(generator: Arc<CompactGenerator>, listener: TcpListener) -> Error {
+    if generator.layout() != CompactLayout::default() {
+        return Error::InvalidLayout("snowflake clients expect the default compact layout");
+    }
+    serve_connections(generator, listener, handle)
+}
+
+fn handle(generator: &CompactGenerator, mut stream: TcpStream) -> io::Result<()> {
+    loop {
+        let mut len = [0; 4];
+        match stream.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        }
+        let len = u32::from_be_bytes(len) as usize;
+        if len > MAX_FRAME_LEN {
+            return Err(invalid("frame too large"));
+        }
+        let mut frame = vec![0; len];
+        stream.read_exact(&mut frame)?;
+        let reply = match call(generator, &frame) {
+            Some(reply) => reply,
+            None => continue,
+        };
+        let mut framed = Vec::with_capacity(4 + reply.len());
+        framed.extend_from_slice(&(reply.len() as u32).to_be_bytes());
+        framed.extend_from_slice(&reply);
+        stream.write_all(&framed)?;
+    }
+}
+
+/// Answers one framed call, or returns `None` for a oneway message.
+fn call(generator: &CompactGenerator, frame: &[u8]) -> Option<Vec<u8>> {
+    let mut input = Input {
+        bytes: frame,
+        pos: 0,
+    };
+    let (name, message_type, seqid) = match input.message_begin() {
+        Ok(header) => header,
+        Err(e) => {
+            return Some(exception("", 0, PROTOCOL_ERROR, &e.to_string()));
+        }
+    };
+    if message_type == ONEWAY {
+        return None;
+    }
+    if message_type != CALL {
+        return Some(exception(&name, seqid, PROTOCOL_ERROR, "expected a call"));
+    }
+    let args = match input.args() {
+        Ok(args) => args,
+        Err(e) => return Some(exception(&name, seqid, PROTOCOL_ERROR, &e.to_string())),
+    };
+    let worker_id = generator.worker_id();
+    let result = match name.as_str() {
+        "get_worker_id" => Ok(worker_id & ((1 << WORKER_ID_BITS) - 1)),
+        "get_datacenter_id" => Ok(worker_id >> WORKER_ID_BITS),
+        "get_timestamp" => Ok(generator
+            .now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or(0)),
+        "get_id" => match args.useragent {
+            Some(ref useragent) if valid_useragent(useragent) => generator
+                .try_generate()
+                .map_err(|e| (INTERNAL_ERROR, e.to_string())),
+            Some(ref useragent) => {
+                Err((INTERNAL_ERROR, format!("invalid useragent {:?}", useragent)))
+            }
+            None => Err((PROTOCOL_ERROR, "missing useragent".to_string())),
+        },
+        _ => Err((UNKNOWN_METHOD, format!("unknown method {:?}", name))),
+    };
+    Some(match result {
+        Ok(value) => success(&name, seqid, value as i64),
+        Err((kind, message)) => exception(&name, seqid, kind, &message),
+    })
+}
+
+// Snowflake only accepts agents matching [a-zA-Z][a-zA-Z\-0-9]*.
+fn valid_useragent(useragent: &str) -> bool {
+    let mut chars = useragent.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '-')
+        }
+        _ => false,
+    }
+}
+
+fn message_begin(out: &mut Vec<u8>, name: &str, message_type: u8, seqid: i32) {
+    out.extend_from_slice(&(VERSION_1 | u32::from(message_type)).to_be_bytes());
+    write_string(out, name);
+    out.extend_from_slice(&seqid.to_be_bytes());
+}
+
+fn write_string(out: &mut Vec<u8>, value: &str) {
+    out.extend_from_slice(&(value.len() as i32).to_be_bytes());
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn success(name: &str, seqid: i32, value: i64) -> Vec<u8> {
+    let mut out = Vec::new();
+    message_begin(&mut out, name, REPLY, seqid);
+    out.push(T_I64);
+    out.extend_from_slice(&0i16.to_be_bytes());
+    out.extend_from_slice(&value.to_be_bytes());
+    out.push(T_STOP);
+    out
+}
+
+fn exception(name: &str, seqid: i32, kind: i32, message: &str) -> Vec<u8> {
+    let mut out = Vec::new();
+    message_begin(&mut out, name, EXCEPTION, seqid);
+    out.push(T_STRING);
+    out.extend_from_slice(&1i16.to_be_bytes());
+    write_string(&mut out, message);
+    out.push(T_I32);
+    out.extend_from_slice(&2i16.to_be_bytes());
+    out.extend_from_slice(&kind.to_be_bytes());
+    out.push(T_STOP);
+    out
+}
+
+/// The arguments of any snowflake method; only `get_id` takes one.
+struct Args {
+    useragent: Option<String>,
+}
+
+/// Reads the binary protocol from a frame.
+struct Input<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Input<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.bytes.len() - self.pos < len {
+            return Err(invalid("message truncated"));
+        }
+        let taken = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn i16(&mut self) -> io::Result<i16> {
+        let mut bytes = [0; 2];
+        bytes.copy_from_slice(self.take(2)?);
+        Ok(i16::from_be_bytes(bytes))
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(i32::from_be_bytes(bytes))
+    }
+
+    fn len(&mut self) -> io::Result<usize> {
+        let len = self.i32()?;
+        if len < 0 {
+            return Err(invalid("negative length"));
+        }
+        Ok(len as usize)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.len()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_e| invalid("string is not UTF-8"))
+    }
+
+    // Accepts both strict messages and the older unversioned form.
+    fn message_begin(&mut self) -> io::Result<(String, u8, i32)> {
+        let first = self.i32()? as u32;
+        if first & 0x8000_0000 != 0 {
+            if first & VERSION_MASK != VERSION_1 {
+                return Err(invalid("unsupported protocol version"));
+            }
+            let name = self.string()?;
+            let seqid = self.i32()?;
+            Ok((name, first as u8, seqid))
+        } else {
+            let name = self.take(first as usize)?;
+            let name =
+                String::from_utf8(name.to_vec()).map_err(|_e| invalid("name is not UTF-8"))?;
+            let message_type = self.byte()?;
+            let seqid = self.i32()?;
+            Ok((name, message_type, seqid))
+        }
+    }
+
+    fn args(&mut self) -> io::Result<Args> {
+        let mut args = Args { useragent: None };
+        loop {
+            let field_type = self.byte()?;
+            if field_type == T_STOP {
+                return Ok(args);
+            }
+            let id = self.i16()?;
+            if id == 1 && field_type == T_STRING {
+                args.useragent = Some(self.string()?);
+            } else {
+                self.skip(field_type, 0)?;
+            }
+        }
+    }
+
+    fn skip(&mut self, field_type: u8, depth: usize) -> io::Result<()> {
+        if depth > 32 {
+            return Err(invalid("nested too deeply"));
+        }
+        match field_type {
+            T_BOOL | T_BYTE => self.take(1).map(|_| ()),
+            T_I16 => self.take(2).map(|_| ()),
+            T_I32 => self.take(4).map(|_| ()),
+            T_DOUBLE | T_I64 => self.take(8).map(|_| ()),
+            T_STRING => {
+                let len = self.len()?;
+                self.take(len).map(|_| ())
+            }
+            T_STRUCT => loop {
+                let field_type = self.byte()?;
+                if field_type == T_STOP {
+                    return Ok(());
+                }
+                self.i16()?;
+                self.skip(field_type, depth + 1)?;
+            },
+            T_MAP => {
+                let (key_type, value_type) = (self.byte()?, self.byte()?);
+                for _ in 0..self.len()? {
+                    self.skip(key_type, depth + 1)?;
+                    self.skip(value_type, depth + 1)?;
+                }
+                Ok(())
+            }
+            T_SET | T_LIST => {
+                let element_type = self.byte()?;
+                for _ in 0..self.len()? {
+                    self.skip(element_type, depth + 1)?;
+                }
+                Ok(())
+            }
+            _ => Err(invalid("unknown field type")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::compact::TWITTER_EPOCH_MS;
+    use std::net::SocketAddr;
+    use std::thread;
+    use std::time::{Duration, SystemTime};
+
+    /// A snowflake client speaking just enough of the protocol.
+    struct Client {
+        stream: TcpStream,
+        seqid: i32,
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Outcome {
+        Value(i64),
+        Exception(i32, String),
+    }
+
+    impl Client {
+        fn connect(addr: SocketAddr) -> Client {
+            Client {
+                stream: TcpStream::connect(addr).unwrap(),
+                seqid: 0,
+            }
+        }
+
+        fn call(&mut self, name: &str, useragent: Option<&str>) -> Outcome {
+            self.seqid += 1;
+            let mut message = Vec::new();
+            message_begin(&mut message, name, CALL, self.seqid);
+            if let Some(useragent) = useragent {
+                message.push(T_STRING);
+                message.extend_from_slice(&1i16.to_be_bytes());
+                write_string(&mut message, useragent);
+            }
+            message.push(T_STOP);
+            self.send(&message)
+        }
+
+        fn send(&mut self, message: &[u8]) -> Outcome {
+            self.stream
+                .write_all(&(message.len() as u32).to_be_bytes())
+                .unwrap();
+            self.stream.write_all(message).unwrap();
+            let mut len = [0; 4];
+            self.stream.read_exact(&mut len).unwrap();
+            let mut frame = vec![0; u32::from_be_bytes(len) as usize];
+            self.stream.read_exact(&mut frame).unwrap();
+            let mut input = Input {
+                bytes: &frame,
+                pos: 0,
+            };
+            let (_name, message_type, seqid) = input.message_begin().unwrap();
+            assert_eq!(seqid, self.seqid);
+            let mut value = 0;
+            let mut message = String::new();
+            let mut kind = 0;
+            loop {
+                let field_type = input.byte().unwrap();
+                if field_type == T_STOP {
+                    break;
+                }
+                match (input.i16().unwrap(), field_type) {
+                    (0, T_I64) => {
+                        let mut bytes = [0; 8];
+                        bytes.copy_from_slice(input.take(8).unwrap());
+                        value = i64::from_be_bytes(bytes);
+                    }
+                    (1, T_STRING) => message = input.string().unwrap(),
+                    (2, T_I32) => kind = input.i32().unwrap(),
+                    _ => panic!("unexpected field"),
+                }
+            }
+            match message_type {
+                REPLY => Outcome::Value(value),
+                EXCEPTION => Outcome::Exception(kind, message),
+                _ => panic!("unexpected message type {}", message_type),
+            }
+        }
+    }
+
+    fn start() -> (SocketAddr, Arc<CompactGenerator>) {
+        let worker_id = snowflake_worker_id(3, 7).unwrap();
+        start_with(CompactGenerator::with_worker_id(worker_id, CompactLayout::default()).unwrap())
+    }
+
+    fn start_with(generator: CompactGenerator) -> (SocketAddr, Arc<CompactGenerator>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let generator = Arc::new(generator);
+        let server = Arc::clone(&generator);
+        thread::spawn(move || serve(server, listener));
+        (addr, generator)
+    }
+
+    #[test]
+    fn test_worker_and_datacenter_ids() {
+        let (addr, _generator) = start();
+        let mut client = Client::connect(addr);
+        assert_eq!(client.call("get_worker_id", None), Outcome::Value(7));
+        assert_eq!(client.call("get_datacenter_id", None), Outcome::Value(3));
+        assert!(matches!(
+            snowflake_worker_id(32, 0),
+            Err(Error::WorkerIdOutOfRange(32))
+        ));
+    }
+
+    #[test]
+    fn test_get_id() {
+        let (addr, generator) = start();
+        let mut client = Client::connect(addr);
+        let first = match client.call("get_id", Some("test-agent")) {
+            Outcome::Value(id) => id as u64,
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        };
+        let second = match client.call("get_id", Some("test-agent")) {
+            Outcome::Value(id) => id as u64,
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        };
+        assert!(first < second);
+        assert_eq!(generator.decode(first).worker_id, (3 << 5) | 7);
+        assert!(matches!(
+            client.call("get_id", Some("1nvalid agent")),
+            Outcome::Exception(INTERNAL_ERROR, _)
+        ));
+    }
+
+    #[test]
+    fn test_get_id_reports_timestamp_overflow() {
+        let epoch = SystemTime::now() - Duration::from_secs(80 * 365 * 86_400);
+        let generator = CompactGenerator::with_worker_id(1, CompactLayout::default())
+            .unwrap()
+            .with_epoch(epoch);
+        let (addr, _generator) = start_with(generator);
+        let mut client = Client::connect(addr);
+        assert!(matches!(
+            client.call("get_id", Some("test-agent")),
+            Outcome::Exception(INTERNAL_ERROR, _)
+        ));
+    }
+
+    #[test]
+    fn test_get_timestamp_uses_the_generator_clock() {
+        let now_ms = TWITTER_EPOCH_MS + 1_234_567;
+        let clock = ManualClock::new(UNIX_EPOCH + Duration::from_millis(now_ms));
+        let generator = CompactGenerator::with_worker_id(1, CompactLayout::default())
+            .unwrap()
+            .with_clock(clock);
+        let (addr, generator) = start_with(generator);
+        let mut client = Client::connect(addr);
+        assert_eq!(
+            client.call("get_timestamp", None),
+            Outcome::Value(now_ms as i64)
+        );
+        let id = match client.call("get_id", Some("test-agent")) {
+            Outcome::Value(id) => id as u64,
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        };
+        assert_eq!(
+            generator.decode(id).timestamp,
+            UNIX_EPOCH + Duration::from_millis(now_ms)
+        );
+    }
+
+    #[test]
+    fn test_unknown_method_and_unversioned_messages() {
+        let (addr, _generator) = start();
+        let mut client = Client::connect(addr);
+        assert!(matches!(
+            client.call("get_name", None),
+            Outcome::Exception(UNKNOWN_METHOD, _)
+        ));
+        client.seqid += 1;
+        let mut message = Vec::new();
+        write_string(&mut message, "get_worker_id");
+        message.push(CALL);
+        message.extend_from_slice(&client.seqid.to_be_bytes());
+        message.push(T_STOP);
+        assert_eq!(client.send(&message), Outcome::Value(7));
+    }
+
+    #[test]
+    fn test_rejects_other_layouts() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let layout = CompactLayout::new(41, 8, 12).unwrap();
+        let generator = Arc::new(CompactGenerator::with_worker_id(1, layout).unwrap());
+        assert!(matches!(
+            serve(generator, listener),
+            Error::InvalidLayout(_)
+        ));
+    }
+}
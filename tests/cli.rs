@@ -0,0 +1,79 @@
+use std::process::{Command, Output};
+
+fn rustflake(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rustflake"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn test_gen_prints_sorted_ids() {
+    let output = rustflake(&["gen", "-n", "5", "-e", "hex", "-s", "01:02:03:04:05:06"]);
+    assert!(output.status.success());
+    let ids: Vec<String> = stdout(&output).lines().map(String::from).collect();
+    assert_eq!(ids.len(), 5);
+    assert!(ids
+        .iter()
+        .all(|id| id.len() == 30 && id[12..24] == *"010203040506"));
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn test_decode_prints_components() {
+    let output = rustflake(&["decode", "AWgGtbwAAQIDBAUGAAAH"]);
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "id: AWgGtbwAAQIDBAUGAAAH\n\
+         timestamp: 2019-01-01T00:00:00.000Z\n\
+         seed: 01:02:03:04:05:06\n\
+         sequence: 7\n"
+    );
+}
+
+#[test]
+fn test_convert_between_formats() {
+    let output = rustflake(&["convert", "-t", "hex", "AWgGtbwAAQIDBAUGAAAH"]);
+    assert_eq!(stdout(&output), "016806b5bc00010203040506000007\n");
+    let output = rustflake(&[
+        "convert",
+        "-f",
+        "hex",
+        "-t",
+        "u128",
+        "016806b5bc00010203040506000007",
+    ]);
+    let value = stdout(&output);
+    let output = rustflake(&["convert", "-f", "u128", "-t", "base64url", value.trim()]);
+    assert_eq!(stdout(&output), "AWgGtbwAAQIDBAUGAAAH\n");
+    let output = rustflake(&["convert", "-t", "u128", "AAAAAAAAAAAAAAAAAAAAAA"]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn test_validate_reports_invalid_ids() {
+    let output = rustflake(&["validate", "AWgGtbwAAQIDBAUGAAAH"]);
+    assert!(output.status.success());
+    let output = rustflake(&["validate", "AWgGtbwAAQIDBAUGAAAH", "AWgGtbwAAQ*DBAUGAAAH"]);
+    assert_eq!(output.status.code(), Some(1));
+    let report = stdout(&output);
+    assert!(report.contains("AWgGtbwAAQIDBAUGAAAH: valid"));
+    assert!(report.contains("AWgGtbwAAQ*DBAUGAAAH: invalid"));
+    // Eight bytes decode as a FlakeId but not as an id `gen` issues.
+    let output = rustflake(&["validate", "AAAAAAAAAAA"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains("AAAAAAAAAAA: invalid"));
+}
+
+#[test]
+fn test_usage_errors() {
+    assert_eq!(rustflake(&[]).status.code(), Some(2));
+    assert_eq!(rustflake(&["mint"]).status.code(), Some(2));
+    assert_eq!(rustflake(&["gen", "-e", "base58"]).status.code(), Some(2));
+    assert_eq!(rustflake(&["decode"]).status.code(), Some(2));
+}
@@ -0,0 +1,105 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::{Child, Command, Stdio};
+
+/// A server on an ephemeral port, killed when dropped.
+struct Server {
+    child: Child,
+    addr: String,
+}
+
+impl Server {
+    fn start(args: &[&str]) -> Server {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_rustflake-http"))
+            .args(["--bind", "127.0.0.1:0", "--seed", "01:02:03:04:05:06"])
+            .args(args)
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut line = String::new();
+        BufReader::new(child.stdout.as_mut().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let addr = line.trim().trim_start_matches("listening on ").to_string();
+        Server { child, addr }
+    }
+
+    fn request(&self, method: &str, path: &str, accept: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(&self.addr).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nAccept: {}\r\nConnection: close\r\n\r\n",
+            method, path, accept
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let body = response.split_once("\r\n\r\n").unwrap().1.to_string();
+        (status, body)
+    }
+
+    fn get(&self, path: &str) -> (u16, String) {
+        self.request("GET", path, "text/plain")
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn test_id_and_ids() {
+    let server = Server::start(&[]);
+    let (status, id) = server.get("/id");
+    assert_eq!(status, 200);
+    assert_eq!(id.trim().len(), 20);
+    let (status, body) = server.get("/ids?count=3");
+    assert_eq!(status, 200);
+    let ids: Vec<&str> = body.lines().collect();
+    assert_eq!(ids.len(), 3);
+    assert!(ids.iter().all(|id| id.len() == 20));
+    assert_eq!(server.get("/ids?count=0").0, 400);
+    assert_eq!(server.get("/ids?count=many").0, 400);
+}
+
+#[test]
+fn test_json_responses() {
+    let server = Server::start(&["--encoding", "hex"]);
+    let (status, body) = server.request("GET", "/id", "application/json");
+    assert_eq!(status, 200);
+    assert!(body.starts_with("{\"id\":\"") && body.trim_end().ends_with("\"}"));
+    assert!(body.contains("010203040506"));
+    let (_, body) = server.get("/ids?count=2&format=json");
+    assert_eq!(body.matches("010203040506").count(), 2);
+    assert_eq!(server.get("/health?format=json").1, "{\"status\":\"ok\"}\n");
+}
+
+#[test]
+fn test_decode() {
+    let server = Server::start(&[]);
+    let (status, body) = server.get("/decode/AWgGtbwAAQIDBAUGAAAH");
+    assert_eq!(status, 200);
+    assert_eq!(
+        body,
+        "id: AWgGtbwAAQIDBAUGAAAH\ntimestamp_ms: 1546300800000\nseed: 01:02:03:04:05:06\nsequence: 7\n"
+    );
+    let (status, body) = server.request("GET", "/decode/AWgGtbwAAQIDBAUGAAAH", "application/json");
+    assert_eq!(status, 200);
+    assert_eq!(
+        body,
+        "{\"id\":\"AWgGtbwAAQIDBAUGAAAH\",\"timestamp_ms\":1546300800000,\"seed\":\"01:02:03:04:05:06\",\"sequence\":7}\n"
+    );
+    assert_eq!(server.get("/decode/not-an-id").0, 400);
+}
+
+#[test]
+fn test_health_and_errors() {
+    let server = Server::start(&[]);
+    assert_eq!(server.get("/health"), (200, "ok\n".to_string()));
+    assert_eq!(server.get("/nope").0, 404);
+    assert_eq!(server.request("POST", "/id", "text/plain").0, 405);
+}